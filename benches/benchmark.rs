@@ -4,12 +4,13 @@ use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
-use bbring::RingBuffer;
+use bbring::{DynRingBuffer, ObjectPool, RingBuffer, ShardedRing, SlotLayout};
 use crossbeam_queue::ArrayQueue;
 
 const QUEUE_CAPACITY: usize = 4096;
 const NUM_OPERATIONS: usize = 100_000_000;
 const NUM_THREADS: usize = 10;
+const BUFFER_SIZE: usize = 4096;
 
 fn bench_spsc(c: &mut Criterion) {
     let mut group = c.benchmark_group("SPSC");
@@ -358,13 +359,274 @@ fn bench_mpmc(c: &mut Criterion) {
     group.finish();
 }
 
+// Runs `NUM_OPERATIONS` items through `queue` with `threads` producers and as many
+// consumers.
+fn run_mpmc<Q: Send + Sync + 'static>(
+    queue: Arc<Q>,
+    threads: usize,
+    push: fn(&Q, usize) -> bool,
+    pop: fn(&Q) -> bool,
+) {
+    let chunk_size = NUM_OPERATIONS / threads;
+
+    let producers = (0..threads)
+        .map(|_| {
+            let q = Arc::clone(&queue);
+            thread::spawn(move || {
+                for i in 0..chunk_size {
+                    while !push(&q, black_box(i)) {
+                        thread::yield_now();
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    let consumers = (0..threads)
+        .map(|_| {
+            let q = Arc::clone(&queue);
+            thread::spawn(move || {
+                let mut consumed_count = 0;
+                while consumed_count < chunk_size {
+                    if pop(&q) {
+                        consumed_count += 1;
+                    } else {
+                        thread::yield_now();
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for p in producers {
+        p.join().unwrap();
+    }
+    for c in consumers {
+        c.join().unwrap();
+    }
+}
+
+fn bench_sharded_mpmc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Sharded_MPMC");
+    group.throughput(Throughput::Elements(NUM_OPERATIONS as u64));
+
+    for threads in [NUM_THREADS, 16, 32, 64] {
+        group.bench_function(format!("BBQ_MPMC_{threads}"), |b| {
+            b.iter(|| {
+                run_mpmc(
+                    Arc::new(RingBuffer::<usize, 64, 64>::new()),
+                    threads,
+                    |q, i| q.push(i).is_ok(),
+                    |q| q.pop().is_some(),
+                )
+            });
+        });
+
+        // Same total capacity as the single ring above.
+        group.bench_function(format!("Sharded_MPMC_{threads}"), |b| {
+            b.iter(|| {
+                run_mpmc(
+                    Arc::new(ShardedRing::<usize, 16, 64>::new(4)),
+                    threads,
+                    |q, i| q.push(i).is_ok(),
+                    |q| q.pop().is_some(),
+                )
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_pool(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Pool");
+    group.throughput(Throughput::Elements(NUM_OPERATIONS as u64));
+
+    group.bench_function("Alloc_Buffer", |b| {
+        b.iter(|| {
+            for i in 0..NUM_OPERATIONS {
+                let mut buf = Vec::<u8>::with_capacity(BUFFER_SIZE);
+                buf.push(i as u8);
+                black_box(&buf);
+            }
+        });
+    });
+
+    group.bench_function("ObjectPool_Buffer", |b| {
+        let pool = ObjectPool::new(16, 64, || Vec::<u8>::with_capacity(BUFFER_SIZE))
+            .with_reset(Vec::clear);
+        b.iter(|| {
+            for i in 0..NUM_OPERATIONS {
+                let mut buf = pool.get();
+                buf.push(i as u8);
+                black_box(&*buf);
+            }
+        });
+    });
+
+    for threads in [4, NUM_THREADS] {
+        group.bench_function(format!("Alloc_Buffer_{threads}"), |b| {
+            b.iter(|| {
+                let handles = (0..threads)
+                    .map(|_| {
+                        thread::spawn(move || {
+                            for i in 0..NUM_OPERATIONS / threads {
+                                let mut buf = Vec::<u8>::with_capacity(BUFFER_SIZE);
+                                buf.push(i as u8);
+                                black_box(&buf);
+                            }
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                for h in handles {
+                    h.join().unwrap();
+                }
+            });
+        });
+
+        group.bench_function(format!("ObjectPool_Buffer_{threads}"), |b| {
+            let pool = Arc::new(
+                ObjectPool::new(16, 64, || Vec::<u8>::with_capacity(BUFFER_SIZE))
+                    .with_reset(Vec::clear),
+            );
+            b.iter(|| {
+                let handles = (0..threads)
+                    .map(|_| {
+                        let pool = Arc::clone(&pool);
+                        thread::spawn(move || {
+                            for i in 0..NUM_OPERATIONS / threads {
+                                let mut buf = pool.get();
+                                buf.push(i as u8);
+                                black_box(&*buf);
+                            }
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                for h in handles {
+                    h.join().unwrap();
+                }
+            });
+        });
+    }
+
+    group.finish();
+}
+
+// Power-of-two geometries against odd ones of about the same capacity.
+fn bench_geometry(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Geometry");
+    group.throughput(Throughput::Elements(NUM_OPERATIONS as u64));
+
+    group.bench_function("BBQ_64x64", |b| {
+        b.iter(|| {
+            run_mpmc(
+                Arc::new(RingBuffer::<usize, 64, 64>::new()),
+                NUM_THREADS,
+                |q, i| q.push(i).is_ok(),
+                |q| q.pop().is_some(),
+            )
+        });
+    });
+    group.bench_function("BBQ_63x65", |b| {
+        b.iter(|| {
+            run_mpmc(
+                Arc::new(RingBuffer::<usize, 63, 65>::new()),
+                NUM_THREADS,
+                |q, i| q.push(i).is_ok(),
+                |q| q.pop().is_some(),
+            )
+        });
+    });
+    group.bench_function("BBQ_8x128", |b| {
+        b.iter(|| {
+            run_mpmc(
+                Arc::new(RingBuffer::<usize, 8, 128>::new()),
+                NUM_THREADS,
+                |q, i| q.push(i).is_ok(),
+                |q| q.pop().is_some(),
+            )
+        });
+    });
+    group.bench_function("BBQ_8x125", |b| {
+        b.iter(|| {
+            run_mpmc(
+                Arc::new(RingBuffer::<usize, 8, 125>::new()),
+                NUM_THREADS,
+                |q, i| q.push(i).is_ok(),
+                |q| q.pop().is_some(),
+            )
+        });
+    });
+
+    // No constants to fold here, so the modulo is a real division.
+    for (block_num, slot_num) in [(64, 64), (63, 65)] {
+        group.bench_function(format!("Dyn_{block_num}x{slot_num}"), |b| {
+            b.iter(|| {
+                run_mpmc(
+                    Arc::new(DynRingBuffer::new(block_num, slot_num)),
+                    NUM_THREADS,
+                    |q, i| q.push(i).is_ok(),
+                    |q| q.pop().is_some(),
+                )
+            });
+        });
+    }
+
+    // The geometry picked for the same capacity and thread count as the rings above.
+    group.bench_function("Dyn_auto", |b| {
+        b.iter(|| {
+            run_mpmc(
+                Arc::new(DynRingBuffer::<usize>::with_capacity(
+                    QUEUE_CAPACITY,
+                    NUM_THREADS,
+                )),
+                NUM_THREADS,
+                |q, i| q.push(i).is_ok(),
+                |q| q.pop().is_some(),
+            )
+        });
+    });
+
+    group.finish();
+}
+
+fn bench_layout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Layout");
+    group.throughput(Throughput::Elements(NUM_OPERATIONS as u64));
+
+    // Few threads barely share a line, many threads fight over them.
+    for threads in [2, NUM_THREADS] {
+        for layout in [SlotLayout::Packed, SlotLayout::Interleaved] {
+            group.bench_function(format!("BBQ_{layout:?}_{threads}"), |b| {
+                b.iter(|| {
+                    run_mpmc(
+                        Arc::new(RingBuffer::<usize, 64, 64>::with_layout(layout)),
+                        threads,
+                        |q, i| q.push(i).is_ok(),
+                        |q| q.pop().is_some(),
+                    )
+                });
+            });
+            group.bench_function(format!("Dyn_{layout:?}_{threads}"), |b| {
+                b.iter(|| {
+                    run_mpmc(
+                        Arc::new(DynRingBuffer::with_layout(64, 64, layout)),
+                        threads,
+                        |q, i| q.push(i).is_ok(),
+                        |q| q.pop().is_some(),
+                    )
+                });
+            });
+        }
+    }
+
+    group.finish();
+}
+
 criterion_group! {
     name = benches;
     config = Criterion::default().sample_size(10).measurement_time(Duration::from_secs(100));
-    // targets = bench_spsc, bench_mpsc, bench_spmc, bench_mpmc
-    // targets = bench_spsc
-    // targets = bench_mpsc
-    // targets = bench_spmc
-    targets = bench_mpmc
+    // Every group takes minutes, pick them by name, like `cargo bench -- MPMC/`.
+    targets = bench_spsc, bench_mpsc, bench_spmc, bench_mpmc, bench_sharded_mpmc, bench_pool,
+        bench_geometry, bench_layout
 }
 criterion_main!(benches);
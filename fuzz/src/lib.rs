@@ -0,0 +1,236 @@
+//! Runs producers and consumers on real threads, but lets only one of them take a step at
+//! a time. Every atomic operation of the ring is a step (see `bbring::hook`), and which
+//! thread takes the next one is read from the fuzzer input, so libFuzzer can search the
+//! interleavings for schedules that lose or duplicate items.
+//!
+//! The input starts with a small header:
+//!
+//! - byte 0: ring geometry,
+//! - byte 1 and 2: number of producers and consumers,
+//! - byte 3: operations per thread,
+//!
+//! and every byte after that picks the thread for one step. Once the input runs out the
+//! threads take turns round-robin until they are done.
+
+use bbring::RingBuffer;
+use bbring::hook;
+
+use std::panic;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+// A schedule that takes more steps than this is stuck, no operation needs that many.
+const MAX_STEPS: usize = 1_000_000;
+
+pub fn run(data: &[u8]) {
+    let mut input = data.iter().copied();
+    let mut header = || input.next().unwrap_or(0) as usize;
+
+    let geometry = header() % 4;
+    let producers = 1 + header() % 3;
+    let consumers = 1 + header() % 3;
+    let ops = 1 + header() % 8;
+
+    match geometry {
+        0 => check::<1, 1>(producers, consumers, ops, input),
+        1 => check::<1, 2>(producers, consumers, ops, input),
+        2 => check::<2, 2>(producers, consumers, ops, input),
+        _ => check::<4, 1>(producers, consumers, ops, input),
+    }
+}
+
+struct State {
+    // Worker allowed to take the next step.
+    turn: Option<usize>,
+    parked: Vec<bool>,
+    done: Vec<bool>,
+}
+
+struct Scheduler {
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+impl Scheduler {
+    fn new(workers: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                turn: None,
+                parked: vec![false; workers],
+                done: vec![false; workers],
+            }),
+            cond: Condvar::new(),
+        }
+    }
+
+    // Called by a worker before each step, returns once it is picked.
+    fn park(&self, id: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.parked[id] = true;
+        self.cond.notify_all();
+        while state.turn != Some(id) {
+            state = self.cond.wait(state).unwrap();
+        }
+        state.turn = None;
+        state.parked[id] = false;
+    }
+
+    fn finish(&self, id: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.done[id] = true;
+        self.cond.notify_all();
+    }
+
+    // Waits until every live worker sits at a step, then lets one of them go.
+    fn drive(&self, mut choices: impl Iterator<Item = u8>) {
+        let mut round_robin = 0;
+
+        for _ in 0..MAX_STEPS {
+            let mut state = self.state.lock().unwrap();
+            while state.turn.is_some()
+                || (0..state.done.len()).any(|i| !state.done[i] && !state.parked[i])
+            {
+                state = self.cond.wait(state).unwrap();
+            }
+
+            let runnable = (0..state.parked.len())
+                .filter(|&i| state.parked[i])
+                .collect::<Vec<_>>();
+            if runnable.is_empty() {
+                return;
+            }
+
+            let pick = match choices.next() {
+                Some(choice) => choice as usize,
+                None => {
+                    round_robin += 1;
+                    round_robin
+                }
+            };
+            state.turn = Some(runnable[pick % runnable.len()]);
+            self.cond.notify_all();
+        }
+
+        panic!("schedule did not finish within {MAX_STEPS} steps");
+    }
+}
+
+// Marks the worker done even if it panics, so the driver does not wait for it forever.
+struct Finish(Arc<Scheduler>, usize);
+
+impl Drop for Finish {
+    fn drop(&mut self) {
+        hook::clear_step_hook();
+        self.0.finish(self.1);
+    }
+}
+
+fn spawn_worker<R: Send + 'static>(
+    sched: &Arc<Scheduler>,
+    id: usize,
+    f: impl FnOnce() -> R + Send + 'static,
+) -> thread::JoinHandle<R> {
+    let sched = Arc::clone(sched);
+    thread::spawn(move || {
+        let _finish = Finish(Arc::clone(&sched), id);
+        let hook_sched = Arc::clone(&sched);
+        hook::set_step_hook(move |_| hook_sched.park(id));
+        sched.park(id);
+        f()
+    })
+}
+
+fn join<R>(handle: thread::JoinHandle<R>) -> R {
+    handle.join().unwrap_or_else(|e| panic::resume_unwind(e))
+}
+
+fn check<const BLOCK_NUM: usize, const SLOT_NUM: usize>(
+    producers: usize,
+    consumers: usize,
+    ops: usize,
+    choices: impl Iterator<Item = u8>,
+) {
+    let q = Arc::new(RingBuffer::<usize, BLOCK_NUM, SLOT_NUM>::new());
+    let sched = Arc::new(Scheduler::new(producers + consumers));
+    let value = move |p: usize, i: usize| p * ops + i;
+
+    let pushers = (0..producers)
+        .map(|p| {
+            let q = Arc::clone(&q);
+            spawn_worker(&sched, p, move || {
+                (0..ops)
+                    .map(|i| q.push(value(p, i)).is_ok())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect::<Vec<_>>();
+    let poppers = (0..consumers)
+        .map(|c| {
+            let q = Arc::clone(&q);
+            spawn_worker(&sched, producers + c, move || {
+                (0..ops).filter_map(|_| q.pop()).collect::<Vec<_>>()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    sched.drive(choices);
+
+    let pushed = pushers.into_iter().map(join).collect::<Vec<_>>();
+    let mut popped = poppers.into_iter().map(join).collect::<Vec<_>>();
+
+    // Whatever is left comes out in one go on this thread, which has no hook.
+    popped.push(std::iter::from_fn(|| q.pop()).collect());
+
+    // Reference model: every successful push comes out exactly once, nothing else comes
+    // out, and a single consumer sees the items of one producer in push order.
+    let mut seen = vec![0; producers * ops];
+    for sequence in &popped {
+        let mut last = vec![None; producers];
+        for &v in sequence {
+            let (p, i) = (v / ops, v % ops);
+            assert!(pushed[p][i], "popped {v} that was never pushed");
+            assert!(
+                last[p] < Some(i),
+                "items of producer {p} out of order: {sequence:?}"
+            );
+            last[p] = Some(i);
+            seen[v] += 1;
+        }
+    }
+    for p in 0..producers {
+        for i in 0..ops {
+            let expected = usize::from(pushed[p][i]);
+            assert_eq!(seen[value(p, i)], expected, "item {i} of producer {p}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+
+    #[test]
+    fn round_robin() {
+        for geometry in 0..4 {
+            for threads in 0..3 {
+                run(&[geometry, threads, threads, 7]);
+            }
+        }
+    }
+
+    #[test]
+    fn pseudo_random_schedules() {
+        let mut x = 0x9e37_79b9_7f4a_7c15_u64;
+        for _ in 0..200 {
+            let data = (0..256)
+                .map(|_| {
+                    x ^= x << 13;
+                    x ^= x >> 7;
+                    x ^= x << 17;
+                    x as u8
+                })
+                .collect::<Vec<_>>();
+            run(&data);
+        }
+    }
+}
@@ -1,42 +1,151 @@
-use crossbeam_utils::{Backoff, CachePadded};
+use crossbeam_utils::CachePadded;
 
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::cell::UnsafeCell;
-use core::cmp::max;
-use core::mem::MaybeUninit;
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::fmt;
+use core::mem::{self, MaybeUninit};
+use core::ptr::NonNull;
+use core::sync::atomic::Ordering;
 
-// BLOCK_NUM and SLOT_NUM must be power of 2
-// only implement retry-new mode now
-pub struct RingBuffer<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
-    head: CachePadded<AtomicUsize>,
-    tail: CachePadded<AtomicUsize>,
-    blocks: [Block<T, SLOT_NUM>; BLOCK_NUM],
+use crate::{DefaultIndex, Index};
+
+// The cache line size `CachePadded` pads to.
+pub(crate) const CACHE_LINE: usize = mem::align_of::<CachePadded<u8>>();
 
-    one_lap: usize,
+// only implement retry-new mode now
+/// A bounded MPMC queue of `BLOCK_NUM` blocks with `SLOT_NUM` slots each.
+///
+/// Both must be non-zero, which is checked when the type is instantiated:
+///
+/// ```compile_fail
+/// let q = bbring::RingBuffer::<u32, 0, 8>::new();
+/// ```
+///
+/// Any other counts work, like `RingBuffer<T, 8, 125>` for a capacity of 1000. A cursor
+/// keeps its block index in the low bits and the version above them, and that index field
+/// is rounded up to a power of two, so splitting a cursor is still a mask. What powers of
+/// two save is the arithmetic on the counts themselves: finding a block's slots is a
+/// multiply and moving to the next block a modulo, which for powers of two compile to
+/// shifts and masks. That only shows on the block-switching path, see the `Geometry`
+/// benchmarks.
+///
+/// `I` is the atomic the cursors and block counters are kept in, see [`Index`]. A narrower
+/// one makes the queue smaller but lets versions wrap sooner:
+///
+/// ```
+/// use std::sync::atomic::AtomicU32;
+///
+/// let q = bbring::RingBuffer::<u32, 4, 16, AtomicU32>::new();
+/// q.push(1).unwrap();
+/// assert_eq!(q.pop(), Some(1));
+/// ```
+pub struct RingBuffer<T, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index = DefaultIndex> {
+    head: CachePadded<I>,
+    tail: CachePadded<I>,
+    blocks: [Block<I>; BLOCK_NUM],
+    slots: [[Slot<T>; SLOT_NUM]; BLOCK_NUM],
+
+    one_lap: u64,
+    interleave: Interleave,
 }
 
-unsafe impl<T: Send, const BLOCK_NUM: usize, const SLOT_NUM: usize> Send
-    for RingBuffer<T, BLOCK_NUM, SLOT_NUM>
+unsafe impl<T: Send, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index> Send
+    for RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>
 {
 }
-unsafe impl<T: Send, const BLOCK_NUM: usize, const SLOT_NUM: usize> Sync
-    for RingBuffer<T, BLOCK_NUM, SLOT_NUM>
+unsafe impl<T: Send, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index> Sync
+    for RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>
 {
 }
 
-struct Block<T, const SLOT_NUM: usize> {
-    allocated: CachePadded<AtomicUsize>,
-    committed: CachePadded<AtomicUsize>, // Actually counter
-    reserved: CachePadded<AtomicUsize>,
-    consumed: CachePadded<AtomicUsize>, // Actually counter
-    slots: [UnsafeCell<MaybeUninit<T>>; SLOT_NUM],
+pub(crate) type Slot<T> = UnsafeCell<MaybeUninit<T>>;
+
+pub(crate) struct Block<I> {
+    allocated: CachePadded<I>,
+    committed: CachePadded<I>, // Actually counter
+    reserved: CachePadded<I>,
+    consumed: CachePadded<I>, // Actually counter
 
-    one_lap: usize,
+    one_lap: u64,
+}
+
+/// How the slots of a block are laid out in memory, see [`RingBuffer::with_layout`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SlotLayout {
+    /// Slot `i` of a block is stored at position `i`, so with a small `T` the slots that
+    /// concurrent producers are handed one after another share a cache line.
+    #[default]
+    Packed,
+    /// Slots one after another are spread over the cache lines of their block, so
+    /// concurrent producers and consumers mostly write different lines. Finding a slot
+    /// costs a division, and items that fill a whole cache line each gain nothing.
+    Interleaved,
+}
+
+// Maps the index of a slot within its block to where it is stored. Interleaving takes
+// the `lines * per_line` slots that fill whole lines and deals them out one per line,
+// round robin, any rest stays in place.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Interleave {
+    lines: usize,
+    per_line: usize,
+}
+
+// One block's slots as seen through its `Interleave`.
+struct BlockSlots<'a, T> {
+    slots: &'a [Slot<T>],
+    interleave: Interleave,
+}
+
+// The queue protocol over borrowed parts, so the same code drives `RingBuffer` with its
+// geometry in the type and `DynRingBuffer` with its geometry chosen at runtime.
+pub(crate) struct Ring<'a, T, I> {
+    pub(crate) head: &'a I,
+    pub(crate) tail: &'a I,
+    pub(crate) blocks: &'a [Block<I>],
+    // `slot_num` slots for every block, back to back.
+    pub(crate) slots: &'a [Slot<T>],
+    pub(crate) slot_num: usize,
+    pub(crate) one_lap: u64,
+    pub(crate) interleave: Interleave,
+}
+
+/// Error returned by [`RingBuffer::push`], handing the rejected value back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushError<T> {
+    /// The queue has no free block to move on to.
+    Full(T),
+    /// The queue was closed with [`RingBuffer::close`].
+    Closed(T),
+}
+
+/// Error returned by [`RingBuffer::try_pop`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopError {
+    /// Nothing is available right now.
+    Empty,
+    /// The queue was closed and everything committed before that has been popped.
+    Closed,
+}
+
+/// Error returned by [`DynRingBuffer::try_new`](crate::DynRingBuffer::try_new) for a
+/// geometry the queue can not run with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The number of blocks is zero.
+    BlockNum,
+    /// The number of slots per block is zero.
+    SlotNum,
+    /// The slots do not fit in a `usize`, or a cursor would need so many index bits that
+    /// fewer than 8 are left for versions, see [`Index`](crate::Index).
+    TooLarge,
 }
 
 enum CommitResult<T> {
     Success,
     BlockDone(T),
+    Closed(T),
 }
 
 enum ConsumeResult<T> {
@@ -57,43 +166,198 @@ enum AdvanceTailReault {
     Success,
 }
 
-impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> RingBuffer<T, BLOCK_NUM, SLOT_NUM> {
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index>
+    RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>
+{
+    // Evaluated when the type is used, so a bad geometry is a compile error.
+    const ONE_LAP: u64 = match one_lap(BLOCK_NUM, SLOT_NUM, I::BITS) {
+        Ok(one_lap) => one_lap,
+        Err(err) => panic!("{}", err.as_str()),
+    };
+
     pub fn new() -> Self {
-        // better error handle
-        if !BLOCK_NUM.is_power_of_two() || !SLOT_NUM.is_power_of_two() {
-            panic!("must be power of two")
+        let mut this = MaybeUninit::uninit();
+        unsafe {
+            Self::init(this.as_mut_ptr());
+            this.assume_init()
         }
+    }
 
-        // may be bug! the overflow of cursor index is a problem
-        let one_lap = max(BLOCK_NUM, SLOT_NUM << 1);
+    /// Creates a queue with its slots laid out as `layout`.
+    ///
+    /// Interleaving pays off when many threads push and pop small items at once. With few
+    /// threads the extra division per access is the larger cost, see the `Layout`
+    /// benchmarks.
+    pub fn with_layout(layout: SlotLayout) -> Self {
+        let mut this = Self::new();
+        this.set_layout(layout);
+        this
+    }
 
-        Self {
-            head: CachePadded::new(AtomicUsize::new(0)),
-            tail: CachePadded::new(AtomicUsize::new(0)),
-            blocks: core::array::from_fn(|i| {
-                if i == 0 {
-                    Block::<T, SLOT_NUM>::new(one_lap, 0)
-                } else {
-                    Block::<T, SLOT_NUM>::new(one_lap, SLOT_NUM)
-                }
-            }),
-            one_lap,
+    /// Creates the queue directly on the heap.
+    ///
+    /// `new` returns every slot by value, so with a large `T` or many slots the queue can
+    /// overflow the stack before it ever reaches a `Box`. This only writes the counters and
+    /// leaves the slots uninitialized in place.
+    pub fn new_boxed() -> Box<Self> {
+        let mut this = Box::<Self>::new_uninit();
+        unsafe {
+            Self::init(this.as_mut_ptr());
+            this.assume_init()
         }
     }
 
-    pub fn push(&self, mut value: T) -> Result<(), T> {
+    /// Creates the queue directly on the heap with its slots laid out as `layout`, see
+    /// [`new_boxed`](RingBuffer::new_boxed) and [`with_layout`](RingBuffer::with_layout).
+    pub fn new_boxed_with_layout(layout: SlotLayout) -> Box<Self> {
+        let mut this = Self::new_boxed();
+        this.set_layout(layout);
+        this
+    }
+
+    // Only for a queue that is still empty, the layout decides where items are found.
+    pub(crate) fn set_layout(&mut self, layout: SlotLayout) {
+        self.interleave = Interleave::new::<T>(layout, SLOT_NUM);
+    }
+
+    // Creates an empty queue whose cursors and blocks start at version `vsn` instead of
+    // zero, to reach the end of the version range without running that many laps.
+    #[cfg(test)]
+    pub(crate) fn starting_at(vsn: u64) -> Self {
+        let this = Self::new();
+        this.head.store(vsn, Ordering::SeqCst);
+        this.tail.store(vsn, Ordering::SeqCst);
+        for (i, blk) in this.blocks.iter().enumerate() {
+            let initial = vsn + if i == 0 { 0 } else { SLOT_NUM as u64 };
+            blk.allocated.store(initial, Ordering::SeqCst);
+            blk.committed.store(initial, Ordering::SeqCst);
+            blk.reserved.store(initial, Ordering::SeqCst);
+            blk.consumed.store(initial, Ordering::SeqCst);
+        }
+        this
+    }
+
+    // Initializes a queue at `this` without touching the slots.
+    //
+    // Safety: `this` must be valid for writes and aligned.
+    pub(crate) unsafe fn init(this: *mut Self) {
+        let one_lap = Self::ONE_LAP;
+
+        unsafe {
+            (&raw mut (*this).head).write(CachePadded::new(I::new(0)));
+            (&raw mut (*this).tail).write(CachePadded::new(I::new(0)));
+            (&raw mut (*this).one_lap).write(one_lap);
+            (&raw mut (*this).interleave).write(Interleave::PACKED);
+
+            let blocks = (&raw mut (*this).blocks).cast::<Block<I>>();
+            for i in 0..BLOCK_NUM {
+                blocks.add(i).write(Block::new(one_lap, SLOT_NUM, i));
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn ring(&self) -> Ring<'_, T, I> {
+        Ring {
+            head: &self.head,
+            tail: &self.tail,
+            blocks: &self.blocks,
+            slots: self.slots.as_flattened(),
+            slot_num: SLOT_NUM,
+            one_lap: self.one_lap,
+            interleave: self.interleave,
+        }
+    }
+
+    /// Pushes `value` at the head.
+    ///
+    /// Blocks are only reused once every item of their previous lap has been popped, so
+    /// a push that has to move on to the next block fails with [`PushError::Full`] while
+    /// that block is partly drained, even though up to `SLOT_NUM - 1` slots are free.
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        self.ring().push(value)
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        self.try_pop().ok()
+    }
+
+    /// Like [`pop`](Self::pop), but tells an empty queue apart from a closed and drained one.
+    pub fn try_pop(&self) -> Result<T, PopError> {
+        self.ring().try_pop()
+    }
+
+    /// Closes the queue for producers.
+    ///
+    /// Every later `push` fails with [`PushError::Closed`], while `try_pop` keeps handing
+    /// out the items committed so far and reports [`PopError::Closed`] once they are gone.
+    /// Returns `false` if the queue was already closed.
+    pub fn close(&self) -> bool {
+        self.ring().close()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.ring().is_closed()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        todo!()
+    }
+
+    pub fn is_full(&self) -> bool {
+        todo!()
+    }
+
+    pub fn len(&self) -> usize {
+        todo!()
+    }
+
+    pub fn capacity(&self) -> usize {
+        BLOCK_NUM * SLOT_NUM
+    }
+
+    /// Returns a copy of the queued items in FIFO order without consuming them.
+    ///
+    /// This reads the slots in place, so it is only a faithful copy in a quiescent state:
+    /// no other thread may push or pop while the snapshot is being taken.
+    pub fn snapshot(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.ring().snapshot()
+    }
+}
+
+impl<T, I: Index> Ring<'_, T, I> {
+    pub(crate) fn push(&self, value: T) -> Result<(), PushError<T>> {
+        self.push_stamped(value, |_, _| {})
+    }
+
+    // Like `push`, and right after the value is written, before it is committed, `stamp`
+    // gets to mark it in its slot with the version of the block it went into.
+    pub(crate) fn push_stamped(
+        &self,
+        mut value: T,
+        stamp: impl Fn(&T, u64),
+    ) -> Result<(), PushError<T>> {
         // let backoff = Backoff::new();
 
         loop {
+            step!(PushLoadHead);
             let head = self.head.load(Ordering::SeqCst);
+            if head & I::CLOSED != 0 {
+                return Err(PushError::Closed(value));
+            }
             let blk_idx = head & (self.one_lap - 1);
 
-            match self.blocks[blk_idx].try_commit(value) {
+            let (blk, slots) = self.block(blk_idx);
+            match blk.try_commit(slots, value, &stamp) {
                 CommitResult::Success => return Ok(()),
+                CommitResult::Closed(val) => return Err(PushError::Closed(val)),
                 CommitResult::BlockDone(val) => {
                     value = val;
                     match self.advance_head(head) {
-                        AdvanceHeadResult::NoEntry => return Err(value),
+                        AdvanceHeadResult::NoEntry => return Err(PushError::Full(value)),
                         AdvanceHeadResult::NotAvaliable => {
                             // backoff.spin();
                             // backoff.snooze();
@@ -105,57 +369,156 @@ impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> RingBuffer<T, BLOCK_NUM,
         }
     }
 
-    pub fn pop(&self) -> Option<T> {
+    pub(crate) fn try_pop(&self) -> Result<T, PopError> {
         // let backoff = Backoff::new();
 
         loop {
+            step!(PopLoadTail);
             let tail = self.tail.load(Ordering::SeqCst);
             let blk_idx = tail & (self.one_lap - 1);
 
-            match self.blocks[blk_idx].try_consume() {
+            let (blk, slots) = self.block(blk_idx);
+            match blk.try_consume(slots) {
                 ConsumeResult::BlockDone => match self.advance_tail(tail) {
-                    AdvanceTailReault::NoEntry => return None,
+                    AdvanceTailReault::NoEntry => return Err(self.empty_or_closed(tail)),
                     AdvanceTailReault::Success => {}
                 },
-                ConsumeResult::NoEntry => return None,
+                ConsumeResult::NoEntry => return Err(self.empty_or_closed(tail)),
                 ConsumeResult::NotAvaliable => {
                     // backoff.spin();
                     // backoff.snooze();
                 }
-                ConsumeResult::Success(val) => return Some(val),
+                ConsumeResult::Success(val) => return Ok(val),
             }
         }
     }
 
-    pub fn is_empty(&self) -> bool {
-        todo!()
+    pub(crate) fn close(&self) -> bool {
+        let head = self.head.fetch_or(I::CLOSED, Ordering::SeqCst);
+        if head & I::CLOSED != 0 {
+            return false;
+        }
+
+        // The head can no longer move, but a producer that loaded it before the close may
+        // still be about to allocate in the head block, or in the block after it if it got
+        // as far as initializing that one. Sealing both makes such an allocation fail, so
+        // once a sealed block drains nothing can show up in it any more.
+        let next = self.next_cursor(head);
+        self.blocks[(head & (self.one_lap - 1)) as usize]
+            .allocated
+            .fetch_or(I::CLOSED, Ordering::SeqCst);
+        self.blocks[(next & (self.one_lap - 1)) as usize]
+            .allocated
+            .fetch_or(I::CLOSED, Ordering::SeqCst);
+        true
     }
 
-    pub fn is_full(&self) -> bool {
-        todo!()
+    pub(crate) fn is_closed(&self) -> bool {
+        self.head.load(Ordering::SeqCst) & I::CLOSED != 0
     }
 
-    pub fn len(&self) -> usize {
-        todo!()
+    pub(crate) fn snapshot(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let mut items = Vec::new();
+        self.for_each_queued(|item| items.push(item.clone()));
+        items
     }
 
-    pub fn capacity(&self) -> usize {
-        BLOCK_NUM * SLOT_NUM
+    // Visit every committed but not yet reserved item from tail to head.
+    pub(crate) fn for_each_queued(&self, mut f: impl FnMut(&T)) {
+        let head = self.head.load(Ordering::SeqCst) & !I::CLOSED;
+        let mut cursor = self.tail.load(Ordering::SeqCst);
+
+        // The tail block may still be the previous lap of the head block, so the walk can
+        // visit one block more than there are.
+        for i in 0..=self.blocks.len() {
+            let (blk, slots) = self.block(cursor & (self.one_lap - 1));
+            let start = if i == 0 {
+                blk.reserved.load(Ordering::SeqCst) & (self.one_lap - 1)
+            } else {
+                0
+            };
+            let end = blk.committed.load(Ordering::SeqCst) & (self.one_lap - 1);
+
+            // `reserved` may already be past `committed`, which reads as nothing queued.
+            for idx in start..end {
+                f(unsafe { (*slots.get(idx).get()).assume_init_ref() });
+            }
+
+            if cursor == head {
+                break;
+            }
+            cursor = self.next_cursor(cursor);
+        }
     }
 
-    fn advance_head(&self, old_head: usize) -> AdvanceHeadResult {
+    // Called when the tail block has nothing to hand out.
+    fn empty_or_closed(&self, tail: u64) -> PopError {
+        let head = self.head.load(Ordering::SeqCst);
+        if head & I::CLOSED == 0 {
+            return PopError::Empty;
+        }
+
+        // Only the two sealed blocks can be the last one holding items: the tail stops at
+        // the head block, or at the block after it if a late producer initialized that one.
+        let head = head & !I::CLOSED;
+        if tail != head && tail != self.next_cursor(head) {
+            return PopError::Empty;
+        }
+
+        let blk = &self.blocks[(tail & (self.one_lap - 1)) as usize];
+        let allocated = blk.allocated.load(Ordering::SeqCst);
+        let committed = blk.committed.load(Ordering::SeqCst);
+        let reserved = blk.reserved.load(Ordering::SeqCst);
+
+        if allocated & I::CLOSED == 0 {
+            // `close` has not sealed this block yet.
+            return PopError::Empty;
+        }
+
+        // A seal that landed before the block was initialized for this lap keeps the old
+        // version, and then nothing can have been allocated in this lap at all.
+        let allocated = allocated & !I::CLOSED;
+        let settled = allocated & !(self.one_lap - 1) != committed & !(self.one_lap - 1)
+            || allocated & (self.one_lap - 1) == committed & (self.one_lap - 1);
+
+        if settled && reserved & (self.one_lap - 1) >= committed & (self.one_lap - 1) {
+            PopError::Closed
+        } else {
+            PopError::Empty
+        }
+    }
+
+    fn next_cursor(&self, cursor: u64) -> u64 {
+        let blk_idx = cursor & (self.one_lap - 1);
+        let vsn = cursor & !(self.one_lap - 1);
+
+        if blk_idx + 1 < self.blocks.len() as u64 {
+            // Same lap, incremented index.
+            cursor + 1
+        } else {
+            // One lap forward, index wraps around to zero.
+            I::wrap(vsn.wrapping_add(self.one_lap))
+        }
+    }
+
+    fn advance_head(&self, old_head: u64) -> AdvanceHeadResult {
         let old_blk_idx = old_head & (self.one_lap - 1);
         let old_head_vsn = old_head & !(self.one_lap - 1);
 
-        let next_blk = &self.blocks[(old_blk_idx + 1) % BLOCK_NUM];
+        let next_blk = &self.blocks[((old_blk_idx + 1) % self.blocks.len() as u64) as usize];
 
+        step!(AdvanceHeadLoadConsumed);
         let next_blk_consumed = next_blk.consumed.load(Ordering::SeqCst);
         let consumed_cnt = next_blk_consumed & (self.one_lap - 1);
         let consumed_vsn = next_blk_consumed & !(self.one_lap - 1);
 
-        // buggy! what if old_head_vsn overflow
-        if consumed_vsn < old_head_vsn || (consumed_vsn == old_head_vsn && consumed_cnt != SLOT_NUM)
+        if I::older(consumed_vsn, old_head_vsn)
+            || (consumed_vsn == old_head_vsn && consumed_cnt != self.slot_num as u64)
         {
+            step!(AdvanceHeadLoadReserved);
             let next_blk_reserved = next_blk.reserved.load(Ordering::SeqCst);
             let reserved_idx = next_blk_reserved & (self.one_lap - 1);
 
@@ -166,68 +529,209 @@ impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> RingBuffer<T, BLOCK_NUM,
             }
         }
 
+        step!(AdvanceHeadInitCommitted);
         next_blk
             .committed
-            .fetch_max(old_head_vsn.wrapping_add(self.one_lap), Ordering::SeqCst);
+            .fetch_newest(I::wrap(old_head_vsn.wrapping_add(self.one_lap)));
+        step!(AdvanceHeadInitAllocated);
         next_blk
             .allocated
-            .fetch_max(old_head_vsn.wrapping_add(self.one_lap), Ordering::SeqCst);
+            .fetch_newest(I::wrap(old_head_vsn.wrapping_add(self.one_lap)));
 
-        let new_head = if old_blk_idx + 1 < BLOCK_NUM {
-            // Same lap, incremented index.
-            old_head + 1
-        } else {
-            // One lap forward, index wraps around to zero.
-            old_head_vsn.wrapping_add(self.one_lap)
-        };
-        self.head.fetch_max(new_head, Ordering::SeqCst);
+        let new_head = self.next_cursor(old_head);
+        step!(AdvanceHeadPublish);
+        self.head.fetch_newest(new_head);
         AdvanceHeadResult::Success
     }
 
-    fn advance_tail(&self, old_tail: usize) -> AdvanceTailReault {
+    fn advance_tail(&self, old_tail: u64) -> AdvanceTailReault {
         let old_blk_idx = old_tail & (self.one_lap - 1);
         let old_tail_vsn = old_tail & !(self.one_lap - 1);
 
-        let next_blk = &self.blocks[(old_blk_idx + 1) % BLOCK_NUM];
+        let next_blk = &self.blocks[((old_blk_idx + 1) % self.blocks.len() as u64) as usize];
+        step!(AdvanceTailLoadCommitted);
         let next_blk_committed = next_blk.committed.load(Ordering::SeqCst);
         let committed_vsn = next_blk_committed & !(self.one_lap - 1);
 
-        if committed_vsn != old_tail_vsn.wrapping_add(self.one_lap) {
+        if committed_vsn != I::wrap(old_tail_vsn.wrapping_add(self.one_lap)) {
             return AdvanceTailReault::NoEntry;
         }
 
+        step!(AdvanceTailInitConsumed);
         next_blk
             .consumed
-            .fetch_max(old_tail_vsn.wrapping_add(self.one_lap), Ordering::SeqCst);
+            .fetch_newest(I::wrap(old_tail_vsn.wrapping_add(self.one_lap)));
+        step!(AdvanceTailInitReserved);
         next_blk
             .reserved
-            .fetch_max(old_tail_vsn.wrapping_add(self.one_lap), Ordering::SeqCst);
+            .fetch_newest(I::wrap(old_tail_vsn.wrapping_add(self.one_lap)));
 
-        let new_tail = if old_blk_idx + 1 < BLOCK_NUM {
-            // Same lap, incremented index.
-            old_tail + 1
-        } else {
-            // One lap forward, index wraps around to zero.
-            old_tail_vsn.wrapping_add(self.one_lap)
-        };
-        self.tail.fetch_max(new_tail, Ordering::SeqCst);
+        let new_tail = self.next_cursor(old_tail);
+        step!(AdvanceTailPublish);
+        self.tail.fetch_newest(new_tail);
         AdvanceTailReault::Success
     }
+
+    fn block(&self, blk_idx: u64) -> (&Block<I>, BlockSlots<'_, T>) {
+        let blk_idx = blk_idx as usize;
+        let start = blk_idx * self.slot_num;
+        let slots = BlockSlots {
+            slots: &self.slots[start..start + self.slot_num],
+            interleave: self.interleave,
+        };
+        (&self.blocks[blk_idx], slots)
+    }
+
+    // Repairs the blocks that a process which died left in the middle of a push or pop,
+    // and returns how many pushes were cut off.
+    //
+    // A slot that was allocated but never committed keeps consumers out of its block for
+    // good, and the block can not simply be cut back, as the head may have moved past it.
+    // Instead every slot of such a block that consumers have not reached is handed to
+    // `seal` with the block's version, see `push_stamped`, which returns `false` for a
+    // slot that was never written after turning it into a hole for consumers to skip, and
+    // then all allocated slots count as committed. A slot that was reserved but never
+    // consumed keeps producers out, so it counts as consumed and its item is lost.
+    //
+    // Safety: nothing else may use the ring during the call.
+    #[cfg(all(feature = "persist", unix, target_has_atomic = "64"))]
+    pub(crate) unsafe fn recover(&self, seal: impl Fn(*mut T, u64) -> bool) -> usize {
+        let mask = self.one_lap - 1;
+        let mut cut_off = 0;
+
+        for blk_idx in 0..self.blocks.len() as u64 {
+            let (blk, slots) = self.block(blk_idx);
+            let allocated = blk.allocated.load(Ordering::SeqCst);
+            let committed = blk.committed.load(Ordering::SeqCst);
+            let reserved = blk.reserved.load(Ordering::SeqCst);
+            let consumed = blk.consumed.load(Ordering::SeqCst);
+
+            let vsn = allocated & !I::CLOSED & !mask;
+            if vsn == committed & !mask && allocated & mask > committed & mask {
+                // Consumers stop at the first uncommitted slot, so all they reserved is whole.
+                let start = if reserved & !mask == vsn {
+                    reserved & mask
+                } else {
+                    0
+                };
+                for idx in start..allocated & mask {
+                    if !seal(slots.get(idx).get().cast(), vsn) {
+                        cut_off += 1;
+                    }
+                }
+                blk.committed
+                    .store(vsn | allocated & mask, Ordering::SeqCst);
+            }
+
+            if reserved & !mask == consumed & !mask && reserved & mask > consumed & mask {
+                blk.consumed.store(reserved, Ordering::SeqCst);
+            }
+        }
+        cut_off
+    }
+
+    // Pops whatever is left, for the owner's `Drop`.
+    pub(crate) fn drain(&self) {
+        // Keeps draining if dropping an item panics, the way dropping a `Vec` does.
+        struct Guard<'r, 'a, T, I: Index>(&'r Ring<'a, T, I>);
+
+        impl<T, I: Index> Drop for Guard<'_, '_, T, I> {
+            fn drop(&mut self) {
+                while self.0.try_pop().is_ok() {}
+            }
+        }
+
+        let guard = Guard(self);
+        while guard.0.try_pop().is_ok() {}
+    }
 }
 
-impl<T, const SLOT_NUM: usize> Block<T, SLOT_NUM> {
-    fn new(one_lap: usize, initial: usize) -> Self {
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index> Default
+    for RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index> Drop
+    for RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>
+{
+    fn drop(&mut self) {
+        self.ring().drain();
+    }
+}
+
+const MIN_VERSION_BITS: u32 = 8;
+
+// Returns the width of the index part of a cursor, or which part of the geometry does
+// not fit. The index has to hold a block index and a slot count up to `2 * slot_num`, and
+// is rounded up to a power of two so that cursors can be split with a mask. Cursors are
+// `bits` wide.
+pub(crate) const fn one_lap(
+    block_num: usize,
+    slot_num: usize,
+    bits: u32,
+) -> Result<u64, ConfigError> {
+    if block_num == 0 {
+        return Err(ConfigError::BlockNum);
+    }
+    if slot_num == 0 {
+        return Err(ConfigError::SlotNum);
+    }
+    if block_num.checked_mul(slot_num).is_none() {
+        return Err(ConfigError::TooLarge);
+    }
+
+    let Some(slots) = slot_num.checked_mul(2) else {
+        return Err(ConfigError::TooLarge);
+    };
+    let width = if block_num > slots { block_num } else { slots };
+    match (width as u64).checked_next_power_of_two() {
+        // Versions are compared within half their range, which needs a few laps of room.
+        Some(one_lap) if one_lap <= 1 << (bits - 1 - MIN_VERSION_BITS) => Ok(one_lap),
+        _ => Err(ConfigError::TooLarge),
+    }
+}
+
+// A zero-sized `T` has nothing to store, so the counters alone turn the queue into a
+// counting semaphore: the value is forgotten on push and conjured again on pop.
+pub(crate) unsafe fn write<T>(slot: &Slot<T>, value: T) {
+    if mem::size_of::<T>() == 0 {
+        mem::forget(value);
+    } else {
+        unsafe { slot.get().write(MaybeUninit::new(value)) }
+    }
+}
+
+pub(crate) unsafe fn read<T>(slot: &Slot<T>) -> T {
+    if mem::size_of::<T>() == 0 {
+        unsafe { NonNull::<T>::dangling().as_ptr().read() }
+    } else {
+        unsafe { slot.get().read().assume_init() }
+    }
+}
+
+impl<I: Index> Block<I> {
+    // The first block starts out empty and all others as if lap zero had already filled and
+    // drained them, so the head can move on to each of them.
+    pub(crate) fn new(one_lap: u64, slot_num: usize, idx: usize) -> Self {
+        let initial = if idx == 0 { 0 } else { slot_num as u64 };
         Self {
-            allocated: CachePadded::new(AtomicUsize::new(initial)),
-            committed: CachePadded::new(AtomicUsize::new(initial)),
-            reserved: CachePadded::new(AtomicUsize::new(initial)),
-            consumed: CachePadded::new(AtomicUsize::new(initial)),
-            slots: core::array::from_fn(|_| UnsafeCell::new(MaybeUninit::uninit())),
+            allocated: CachePadded::new(I::new(initial)),
+            committed: CachePadded::new(I::new(initial)),
+            reserved: CachePadded::new(I::new(initial)),
+            consumed: CachePadded::new(I::new(initial)),
             one_lap,
         }
     }
 
-    fn try_commit(&self, value: T) -> CommitResult<T> {
+    fn try_commit<T>(
+        &self,
+        slots: BlockSlots<'_, T>,
+        value: T,
+        stamp: &impl Fn(&T, u64),
+    ) -> CommitResult<T> {
         // // annoyying part is here
         // // In fact in retry-new mode you the allocated-version actually not matter
         // // what need prevent is for example, ringbuffer config is BLOCK_NUM = 4, SLOT_NUM = 2,
@@ -276,31 +780,47 @@ impl<T, const SLOT_NUM: usize> Block<T, SLOT_NUM> {
         // Attention!!!
         // The performace must be worse than FAA but for the correctness i use this first
         loop {
+            step!(CommitLoadAllocated);
             let allocated = self.allocated.load(Ordering::SeqCst);
             let allocated_idx = allocated & (self.one_lap - 1);
 
-            if allocated_idx >= SLOT_NUM {
+            // A seal that lands after this load makes the exchange below fail.
+            if allocated & I::CLOSED != 0 {
+                return CommitResult::Closed(value);
+            }
+
+            if allocated_idx >= slots.len() {
                 return CommitResult::BlockDone(value);
             }
 
-            if self.allocated.fetch_max(allocated + 1, Ordering::SeqCst) == allocated {
-                unsafe {
-                    self.slots[allocated_idx]
-                        .get()
-                        .write(MaybeUninit::new(value));
-                }
+            step!(CommitAllocate);
+            if self
+                .allocated
+                .compare_exchange(allocated, allocated + 1)
+                .is_ok()
+            {
+                let slot = slots.get(allocated_idx);
+                step!(CommitWrite);
+                unsafe { write(slot, value) };
+                stamp(
+                    unsafe { &*slot.get().cast::<T>() },
+                    allocated & !(self.one_lap - 1),
+                );
+                step!(CommitPublish);
                 self.committed.fetch_add(1, Ordering::SeqCst);
                 return CommitResult::Success;
             }
         }
     }
 
-    fn try_consume(&self) -> ConsumeResult<T> {
+    fn try_consume<T>(&self, slots: BlockSlots<'_, T>) -> ConsumeResult<T> {
         loop {
+            step!(ConsumeLoadReserved);
             let reserved = self.reserved.load(Ordering::SeqCst);
             let reserved_idx = reserved & (self.one_lap - 1);
 
-            if reserved_idx < SLOT_NUM {
+            if reserved_idx < slots.len() {
+                step!(ConsumeLoadCommitted);
                 let committed = self.committed.load(Ordering::SeqCst);
                 let committed_cnt = committed & (self.one_lap - 1);
 
@@ -308,7 +828,8 @@ impl<T, const SLOT_NUM: usize> Block<T, SLOT_NUM> {
                     return ConsumeResult::NoEntry;
                 }
 
-                if committed_cnt != SLOT_NUM {
+                if committed_cnt != slots.len() {
+                    step!(ConsumeLoadAllocated);
                     let allocated = self.allocated.load(Ordering::SeqCst);
                     let allocated_idx = allocated & (self.one_lap - 1);
 
@@ -317,8 +838,14 @@ impl<T, const SLOT_NUM: usize> Block<T, SLOT_NUM> {
                     }
                 }
 
-                if self.reserved.fetch_max(reserved + 1, Ordering::SeqCst) == reserved {
-                    let data = unsafe { self.slots[reserved_idx].get().read().assume_init() };
+                step!(ConsumeReserve);
+                if self
+                    .reserved
+                    .compare_exchange(reserved, reserved + 1)
+                    .is_ok()
+                {
+                    let data = unsafe { read(slots.get(reserved_idx)) };
+                    step!(ConsumePublish);
                     self.consumed.fetch_add(1, Ordering::SeqCst);
                     return ConsumeResult::Success(data);
                 }
@@ -328,3 +855,94 @@ impl<T, const SLOT_NUM: usize> Block<T, SLOT_NUM> {
         }
     }
 }
+
+impl Interleave {
+    pub(crate) const PACKED: Self = Self {
+        lines: 1,
+        per_line: 0,
+    };
+
+    pub(crate) fn new<T>(layout: SlotLayout, slot_num: usize) -> Self {
+        let size = mem::size_of::<T>();
+        if layout == SlotLayout::Packed || size == 0 {
+            return Self::PACKED;
+        }
+
+        let per_line = CACHE_LINE / size;
+        let lines = slot_num / per_line.max(1);
+        if per_line < 2 || lines < 2 {
+            return Self::PACKED;
+        }
+        Self { lines, per_line }
+    }
+
+    #[inline]
+    fn slot(&self, idx: usize) -> usize {
+        if self.lines == 1 || idx >= self.lines * self.per_line {
+            idx
+        } else {
+            (idx % self.lines) * self.per_line + idx / self.lines
+        }
+    }
+}
+
+impl<'a, T> BlockSlots<'a, T> {
+    #[inline]
+    fn len(&self) -> u64 {
+        self.slots.len() as u64
+    }
+
+    #[inline]
+    fn get(&self, idx: u64) -> &'a Slot<T> {
+        &self.slots[self.interleave.slot(idx as usize)]
+    }
+}
+
+impl<T> PushError<T> {
+    /// Returns the value that could not be pushed.
+    pub fn into_inner(self) -> T {
+        match self {
+            PushError::Full(value) | PushError::Closed(value) => value,
+        }
+    }
+}
+
+impl<T> fmt::Display for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::Full(_) => f.write_str("push on a full queue"),
+            PushError::Closed(_) => f.write_str("push on a closed queue"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> core::error::Error for PushError<T> {}
+
+impl fmt::Display for PopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PopError::Empty => f.write_str("pop on an empty queue"),
+            PopError::Closed => f.write_str("pop on a closed and drained queue"),
+        }
+    }
+}
+
+impl core::error::Error for PopError {}
+
+impl ConfigError {
+    const fn as_str(&self) -> &'static str {
+        match self {
+            ConfigError::BlockNum => "BLOCK_NUM must be non-zero",
+            ConfigError::SlotNum => "SLOT_NUM must be non-zero",
+            ConfigError::TooLarge => "BLOCK_NUM or SLOT_NUM too large for the cursor",
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl core::error::Error for ConfigError {}
@@ -0,0 +1,435 @@
+use crate::{DefaultIndex, PushError};
+use crossbeam_utils::{Backoff, CachePadded};
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+// Marks a subscriber slot nobody holds.
+const FREE: u64 = u64::MAX;
+
+/// What a producer does when the block it wants to reuse has not been read by everyone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadcastPolicy {
+    /// Reuse it anyway. Subscribers that fall a whole ring behind skip ahead and are told
+    /// how many items they missed.
+    Lagging,
+    /// Refuse the push with [`PushError::Full`] until the slowest subscriber moves on.
+    Blocking,
+}
+
+/// Error returned by [`Subscriber::recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// Nothing new has been published.
+    Empty,
+    /// The subscriber fell behind and exactly this many items were overwritten before it
+    /// could read them. The next `recv` continues with the oldest item still available.
+    Lagged(usize),
+}
+
+/// A ring where every subscriber sees every item.
+///
+/// It keeps the block layout of `RingBuffer`, but items are cloned out instead of moved
+/// out, so there is no shared tail. Every subscriber walks the blocks with its own cursor
+/// and a block is only recycled once the head comes around to it again. Under the
+/// blocking policy that additionally waits for the minimum of the subscriber cursors to
+/// pass the block.
+///
+/// Blocks are numbered by a sequence that only grows, and their counters hold that
+/// sequence next to the slot count, like the version bits of `RingBuffer`. They are 64
+/// bits on every target, so the sequence does not wrap before more than 2^63 items were
+/// pushed, which takes centuries at a billion pushes a second.
+pub struct BroadcastRing<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    head: CachePadded<DefaultIndex>,
+    blocks: [Block<T, SLOT_NUM>; BLOCK_NUM],
+    // Block sequence every subscriber is reading, or `FREE`.
+    subscribers: Box<[CachePadded<DefaultIndex>]>,
+
+    policy: BroadcastPolicy,
+    one_lap: u64,
+}
+
+unsafe impl<T: Send + Sync, const BLOCK_NUM: usize, const SLOT_NUM: usize> Send
+    for BroadcastRing<T, BLOCK_NUM, SLOT_NUM>
+{
+}
+unsafe impl<T: Send + Sync, const BLOCK_NUM: usize, const SLOT_NUM: usize> Sync
+    for BroadcastRing<T, BLOCK_NUM, SLOT_NUM>
+{
+}
+
+struct Block<T, const SLOT_NUM: usize> {
+    allocated: CachePadded<DefaultIndex>,
+    committed: CachePadded<DefaultIndex>, // Actually counter
+    // Items pushed to the ring before this block, written when the block is handed over
+    // and before `allocated` moves to the new sequence.
+    start: DefaultIndex,
+    // Subscribers cloning out of this block right now.
+    readers: CachePadded<AtomicUsize>,
+    slots: [UnsafeCell<MaybeUninit<T>>; SLOT_NUM],
+}
+
+/// A read cursor into a [`BroadcastRing`].
+pub struct Subscriber<'a, T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    ring: &'a BroadcastRing<T, BLOCK_NUM, SLOT_NUM>,
+    slot: usize,
+    seq: u64,
+    idx: usize,
+    // `start` of the block at `seq`.
+    start: u64,
+}
+
+enum CommitResult<T> {
+    Success,
+    BlockDone(T),
+    NotAvaliable(T),
+}
+
+enum AdvanceHeadResult {
+    Success,
+    NoEntry,
+    NotAvaliable,
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> BroadcastRing<T, BLOCK_NUM, SLOT_NUM> {
+    /// Creates a ring with room for at most `max_subscribers` subscribers at a time.
+    pub fn new(policy: BroadcastPolicy, max_subscribers: usize) -> Self {
+        // A single block would have to be recycled while the head still points at it.
+        if BLOCK_NUM < 2 || SLOT_NUM == 0 {
+            panic!("need at least two non-empty blocks")
+        }
+
+        let one_lap = (SLOT_NUM as u64 + 1).next_power_of_two();
+
+        // Start one lap in, so that every block but the head one looks like a drained
+        // block of the previous lap.
+        Self {
+            head: CachePadded::new(DefaultIndex::new(BLOCK_NUM as u64)),
+            blocks: core::array::from_fn(|i| {
+                let seq = if i == 0 { BLOCK_NUM } else { i };
+                Block::new(seq as u64 * one_lap)
+            }),
+            subscribers: (0..max_subscribers)
+                .map(|_| CachePadded::new(DefaultIndex::new(FREE)))
+                .collect(),
+            policy,
+            one_lap,
+        }
+    }
+
+    /// Publishes `value` to every current subscriber.
+    pub fn push(&self, mut value: T) -> Result<(), PushError<T>> {
+        let backoff = Backoff::new();
+
+        loop {
+            let head = self.head.load(Ordering::SeqCst);
+
+            match self.block(head).try_commit(head, self.one_lap, value) {
+                CommitResult::Success => return Ok(()),
+                CommitResult::NotAvaliable(val) => {
+                    value = val;
+                    backoff.snooze();
+                }
+                CommitResult::BlockDone(val) => {
+                    value = val;
+                    match self.advance_head(head) {
+                        AdvanceHeadResult::NoEntry => return Err(PushError::Full(value)),
+                        AdvanceHeadResult::NotAvaliable => backoff.snooze(),
+                        AdvanceHeadResult::Success => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Registers a subscriber that sees everything pushed from now on, or `None` if all
+    /// subscriber slots are taken.
+    pub fn subscribe(&self) -> Option<Subscriber<'_, T, BLOCK_NUM, SLOT_NUM>> {
+        let slot = self.subscribers.iter().position(|s| {
+            s.compare_exchange(
+                FREE,
+                self.head.load(Ordering::SeqCst),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+        })?;
+
+        // Publish the cursor before reading where to start, so that a producer about to
+        // recycle the block either sees the cursor or has already moved the head.
+        let (seq, allocated, start) = loop {
+            let head = self.head.load(Ordering::SeqCst);
+            self.subscribers[slot].store(head, Ordering::SeqCst);
+            let blk = self.block(head);
+            let allocated = blk.allocated.load(Ordering::SeqCst);
+            let start = blk.start.load(Ordering::SeqCst);
+            if self.head.load(Ordering::SeqCst) == head && allocated / self.one_lap == head {
+                break (head, allocated, start);
+            }
+        };
+
+        Some(Subscriber {
+            ring: self,
+            slot,
+            seq,
+            idx: ((allocated % self.one_lap) as usize).min(SLOT_NUM),
+            start,
+        })
+    }
+
+    pub fn policy(&self) -> BroadcastPolicy {
+        self.policy
+    }
+
+    pub fn capacity(&self) -> usize {
+        BLOCK_NUM * SLOT_NUM
+    }
+
+    fn block(&self, seq: u64) -> &Block<T, SLOT_NUM> {
+        &self.blocks[(seq % BLOCK_NUM as u64) as usize]
+    }
+
+    fn advance_head(&self, old_head: u64) -> AdvanceHeadResult {
+        let next = old_head + 1;
+        let next_blk = self.block(next);
+
+        let committed = next_blk.committed.load(Ordering::SeqCst);
+        if committed / self.one_lap >= next {
+            // Someone else already took the block over.
+            self.head.fetch_max(next, Ordering::SeqCst);
+            return AdvanceHeadResult::Success;
+        }
+
+        if self.policy == BroadcastPolicy::Blocking
+            && self.min_subscriber() <= next - BLOCK_NUM as u64
+        {
+            return AdvanceHeadResult::NoEntry;
+        }
+
+        // Late producers of the previous lap must finish before the block is reused.
+        if next_blk.allocated.load(Ordering::SeqCst) != committed {
+            return AdvanceHeadResult::NotAvaliable;
+        }
+
+        // Moving `committed` to the new sequence first turns away new readers, and the one
+        // producer that wins it recycles the block.
+        if next_blk
+            .committed
+            .compare_exchange(
+                committed,
+                next * self.one_lap,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_err()
+        {
+            return AdvanceHeadResult::NotAvaliable;
+        }
+
+        let backoff = Backoff::new();
+        while next_blk.readers.load(Ordering::SeqCst) != 0 {
+            backoff.snooze();
+        }
+
+        for slot in &next_blk.slots[..(committed % self.one_lap) as usize] {
+            unsafe { (*slot.get()).assume_init_drop() };
+        }
+
+        // The old head block is full, nothing more is allocated in it, and it can not be
+        // recycled before the head has moved past this block.
+        let old_blk = self.block(old_head);
+        let pushed = old_blk.allocated.load(Ordering::SeqCst) % self.one_lap;
+        next_blk.start.store(
+            old_blk.start.load(Ordering::SeqCst) + pushed,
+            Ordering::SeqCst,
+        );
+
+        next_blk
+            .allocated
+            .store(next * self.one_lap, Ordering::SeqCst);
+        self.head.fetch_max(next, Ordering::SeqCst);
+        AdvanceHeadResult::Success
+    }
+
+    fn min_subscriber(&self) -> u64 {
+        self.subscribers
+            .iter()
+            .map(|s| s.load(Ordering::SeqCst))
+            .min()
+            .unwrap_or(FREE)
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Drop
+    for BroadcastRing<T, BLOCK_NUM, SLOT_NUM>
+{
+    fn drop(&mut self) {
+        for blk in &mut self.blocks {
+            let committed = (*blk.committed.get_mut() % self.one_lap) as usize;
+            for slot in &mut blk.slots[..committed] {
+                unsafe { slot.get_mut().assume_init_drop() };
+            }
+        }
+    }
+}
+
+impl<T, const SLOT_NUM: usize> Block<T, SLOT_NUM> {
+    fn new(initial: u64) -> Self {
+        Self {
+            allocated: CachePadded::new(DefaultIndex::new(initial)),
+            committed: CachePadded::new(DefaultIndex::new(initial)),
+            start: DefaultIndex::new(0),
+            readers: CachePadded::new(AtomicUsize::new(0)),
+            slots: core::array::from_fn(|_| UnsafeCell::new(MaybeUninit::uninit())),
+        }
+    }
+
+    fn try_commit(&self, seq: u64, one_lap: u64, value: T) -> CommitResult<T> {
+        loop {
+            let allocated = self.allocated.load(Ordering::SeqCst);
+            let allocated_idx = (allocated % one_lap) as usize;
+
+            if allocated / one_lap < seq {
+                // The head moved here before the block was handed over.
+                return CommitResult::NotAvaliable(value);
+            }
+
+            if allocated / one_lap > seq || allocated_idx >= SLOT_NUM {
+                return CommitResult::BlockDone(value);
+            }
+
+            if self.allocated.fetch_max(allocated + 1, Ordering::SeqCst) == allocated {
+                unsafe {
+                    self.slots[allocated_idx]
+                        .get()
+                        .write(MaybeUninit::new(value));
+                }
+                self.committed.fetch_add(1, Ordering::SeqCst);
+                return CommitResult::Success;
+            }
+        }
+    }
+}
+
+impl<T: Clone, const BLOCK_NUM: usize, const SLOT_NUM: usize>
+    Subscriber<'_, T, BLOCK_NUM, SLOT_NUM>
+{
+    /// Returns a clone of the next item this subscriber has not seen yet.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let ring = self.ring;
+        let one_lap = ring.one_lap;
+
+        loop {
+            if self.idx == SLOT_NUM {
+                let next = ring.block(self.seq + 1);
+                let committed = next.committed.load(Ordering::SeqCst);
+                match (committed / one_lap).cmp(&(self.seq + 1)) {
+                    core::cmp::Ordering::Less => return Err(RecvError::Empty),
+                    core::cmp::Ordering::Equal => {
+                        // The block is handed over once `allocated` moved too, and only
+                        // then is its `start` written.
+                        if next.allocated.load(Ordering::SeqCst) / one_lap != self.seq + 1 {
+                            return Err(RecvError::Empty);
+                        }
+                        let start = next.start.load(Ordering::SeqCst);
+                        if next.committed.load(Ordering::SeqCst) / one_lap != self.seq + 1 {
+                            continue;
+                        }
+                        self.seq += 1;
+                        self.idx = 0;
+                        self.start = start;
+                        ring.subscribers[self.slot].store(self.seq, Ordering::SeqCst);
+                        continue;
+                    }
+                    core::cmp::Ordering::Greater => return Err(self.skip_lagged()),
+                }
+            }
+
+            let blk = ring.block(self.seq);
+
+            // Announce the read before checking the block, a producer that recycles it
+            // after this check waits for `readers` to drop back.
+            blk.readers.fetch_add(1, Ordering::SeqCst);
+            let committed = blk.committed.load(Ordering::SeqCst);
+
+            if committed / one_lap != self.seq {
+                blk.readers.fetch_sub(1, Ordering::SeqCst);
+                return if committed / one_lap > self.seq {
+                    Err(self.skip_lagged())
+                } else {
+                    Err(RecvError::Empty)
+                };
+            }
+
+            let committed_cnt = (committed % one_lap) as usize;
+            let ready = self.idx < committed_cnt
+                && (committed_cnt == SLOT_NUM || blk.allocated.load(Ordering::SeqCst) == committed);
+
+            let value = if ready {
+                Some(unsafe { (*blk.slots[self.idx].get()).assume_init_ref().clone() })
+            } else {
+                None
+            };
+            blk.readers.fetch_sub(1, Ordering::SeqCst);
+
+            return match value {
+                Some(value) => {
+                    self.idx += 1;
+                    Ok(value)
+                }
+                None => Err(RecvError::Empty),
+            };
+        }
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Subscriber<'_, T, BLOCK_NUM, SLOT_NUM> {
+    // The block under the cursor was recycled, so jump to the oldest block that is
+    // still around and report what was lost on the way, counted from where the block
+    // jumped to starts.
+    fn skip_lagged(&mut self) -> RecvError {
+        let ring = self.ring;
+
+        let (seq, start) = loop {
+            let head = ring.head.load(Ordering::SeqCst);
+            let seq = (head + 1 - BLOCK_NUM as u64).max(self.seq + 1);
+            let blk = ring.block(seq);
+            let start = blk.start.load(Ordering::SeqCst);
+            // It is at or behind the head, so handed over already, check that it was not
+            // recycled again while reading `start`.
+            if blk.allocated.load(Ordering::SeqCst) / ring.one_lap == seq
+                && blk.committed.load(Ordering::SeqCst) / ring.one_lap == seq
+            {
+                break (seq, start);
+            }
+        };
+        let missed = start - (self.start + self.idx as u64);
+
+        self.seq = seq;
+        self.idx = 0;
+        self.start = start;
+        ring.subscribers[self.slot].store(seq, Ordering::SeqCst);
+        RecvError::Lagged(missed as usize)
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Drop
+    for Subscriber<'_, T, BLOCK_NUM, SLOT_NUM>
+{
+    fn drop(&mut self) {
+        self.ring.subscribers[self.slot].store(FREE, Ordering::SeqCst);
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Empty => f.write_str("receive on an empty broadcast ring"),
+            RecvError::Lagged(n) => write!(f, "subscriber lagged behind by {n} items"),
+        }
+    }
+}
+
+impl std::error::Error for RecvError {}
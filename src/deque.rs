@@ -0,0 +1,303 @@
+use crate::PushError;
+use crate::bbring::{Slot, read, write};
+use crossbeam_utils::CachePadded;
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Blocks are numbered by how often the owner has moved on to a new one, and block `n`
+// lives at position `n % BLOCK_NUM`. The full blocks `top..bottom` can be stolen, and
+// block `bottom` is the one the owner is filling.
+struct Inner<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    top: CachePadded<AtomicUsize>,
+    bottom: CachePadded<AtomicUsize>,
+    // The block number each position may be filled with next. A stolen block only bumps
+    // it by a lap once the thief is done reading it.
+    next_lap: [CachePadded<AtomicUsize>; BLOCK_NUM],
+    slots: [[Slot<T>; SLOT_NUM]; BLOCK_NUM],
+}
+
+unsafe impl<T: Send, const BLOCK_NUM: usize, const SLOT_NUM: usize> Send
+    for Inner<T, BLOCK_NUM, SLOT_NUM>
+{
+}
+unsafe impl<T: Send, const BLOCK_NUM: usize, const SLOT_NUM: usize> Sync
+    for Inner<T, BLOCK_NUM, SLOT_NUM>
+{
+}
+
+/// The owner's end of a work-stealing deque built from `BLOCK_NUM` blocks of `SLOT_NUM`
+/// slots.
+///
+/// The owner pushes and pops at the same end, so it sees its newest work first. Every
+/// block it fills becomes visible to the [`Stealer`]s, which take whole blocks from the
+/// other end with [`Stealer::steal_batch`], oldest block first.
+///
+/// Popping back into a full block costs one load of the thieves' end, and only racing a
+/// thief for the very last full block costs a CAS. Like [`RingBuffer`](crate::RingBuffer),
+/// a push fails with [`PushError::Full`] when the next block is still held by a thief,
+/// even though other blocks may be free.
+pub struct Worker<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    inner: Arc<Inner<T, BLOCK_NUM, SLOT_NUM>>,
+    // Items in block `bottom`.
+    len: Cell<usize>,
+    // A block won from the thieves, with the number of items left in it.
+    taken: Cell<Option<(usize, usize)>>,
+}
+
+/// The thieves' end of a [`Worker`], cheap to clone and share between threads.
+pub struct Stealer<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    inner: Arc<Inner<T, BLOCK_NUM, SLOT_NUM>>,
+}
+
+/// Outcome of [`Stealer::steal_batch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Steal<T> {
+    /// No full block was there to steal.
+    Empty,
+    /// A whole block was stolen.
+    Success(T),
+    /// Lost a race with another thief or the owner, try again.
+    Retry,
+}
+
+/// The items of one stolen block, oldest first.
+///
+/// The owner can not refill the block while this is alive, and dropping it drops the
+/// items that were not taken.
+pub struct Batch<'a, T> {
+    slots: &'a [Slot<T>],
+    next: usize,
+    next_lap: &'a AtomicUsize,
+    lap: usize,
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Worker<T, BLOCK_NUM, SLOT_NUM> {
+    /// Panics unless there are at least two blocks and `SLOT_NUM` is non-zero.
+    pub fn new() -> Self {
+        if BLOCK_NUM < 2 || SLOT_NUM == 0 {
+            panic!("must have at least two blocks of at least one slot")
+        }
+
+        // Built in place, so large blocks never pass through the stack.
+        let mut inner = Arc::<Inner<T, BLOCK_NUM, SLOT_NUM>>::new_uninit();
+        let this = Arc::get_mut(&mut inner).unwrap().as_mut_ptr();
+        unsafe {
+            (&raw mut (*this).top).write(CachePadded::new(AtomicUsize::new(0)));
+            (&raw mut (*this).bottom).write(CachePadded::new(AtomicUsize::new(0)));
+            let next_lap = (&raw mut (*this).next_lap).cast::<CachePadded<AtomicUsize>>();
+            for i in 0..BLOCK_NUM {
+                next_lap.add(i).write(CachePadded::new(AtomicUsize::new(i)));
+            }
+        }
+
+        Self {
+            inner: unsafe { inner.assume_init() },
+            len: Cell::new(0),
+            taken: Cell::new(None),
+        }
+    }
+
+    pub fn stealer(&self) -> Stealer<T, BLOCK_NUM, SLOT_NUM> {
+        Stealer {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+
+    /// Pushes `value` at the owner's end.
+    ///
+    /// Never returns [`PushError::Closed`], there is no closing a deque.
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        let inner = &*self.inner;
+        let bottom = inner.bottom.load(Ordering::SeqCst);
+
+        // A full block that could not be handed to the thieves yet.
+        if self.len.get() == SLOT_NUM && !self.publish(bottom) {
+            return Err(PushError::Full(value));
+        }
+
+        let bottom = inner.bottom.load(Ordering::SeqCst);
+        let len = self.len.get();
+        unsafe { write(&inner.slots[bottom % BLOCK_NUM][len], value) };
+        self.len.set(len + 1);
+
+        if len + 1 == SLOT_NUM {
+            self.publish(bottom);
+        }
+        Ok(())
+    }
+
+    /// Pops the newest item.
+    pub fn pop(&self) -> Option<T> {
+        if self.len.get() == 0 && self.taken.get().is_none() {
+            self.take_back();
+        }
+        self.pop_local()
+    }
+
+    pub fn capacity(&self) -> usize {
+        BLOCK_NUM * SLOT_NUM
+    }
+
+    // Hands the full block `bottom` to the thieves and moves on to the next one, unless
+    // that one is still being stolen from.
+    fn publish(&self, bottom: usize) -> bool {
+        let inner = &*self.inner;
+        let next = bottom.wrapping_add(1);
+        if inner.next_lap[next % BLOCK_NUM].load(Ordering::SeqCst) != next {
+            return false;
+        }
+
+        inner.bottom.store(next, Ordering::SeqCst);
+        self.len.set(0);
+        true
+    }
+
+    // Moves back into the newest full block. Lowering `bottom` first keeps the thieves off
+    // it unless it is also the oldest one, and only then is there a race to win with a
+    // CAS on `top`, as in a Chase-Lev deque.
+    fn take_back(&self) {
+        let inner = &*self.inner;
+        let bottom = inner.bottom.load(Ordering::SeqCst);
+        let prev = bottom.wrapping_sub(1);
+
+        inner.bottom.store(prev, Ordering::SeqCst);
+        let top = inner.top.load(Ordering::SeqCst);
+        let ahead = prev.wrapping_sub(top) as isize;
+
+        if ahead > 0 {
+            // Out of reach of the thieves now, so it is the owner's block again.
+            self.len.set(SLOT_NUM);
+            return;
+        }
+
+        if ahead == 0
+            && inner
+                .top
+                .compare_exchange(top, top.wrapping_add(1), Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        {
+            // Won it, but it now sits before `top`, so it is kept aside until drained.
+            self.taken.set(Some((prev, SLOT_NUM)));
+        }
+        inner.bottom.store(bottom, Ordering::SeqCst);
+    }
+
+    // Pops from the owner's block, or else from the block won from the thieves.
+    fn pop_local(&self) -> Option<T> {
+        let inner = &*self.inner;
+
+        let len = self.len.get();
+        if len > 0 {
+            let bottom = inner.bottom.load(Ordering::SeqCst);
+            self.len.set(len - 1);
+            return Some(unsafe { read(&inner.slots[bottom % BLOCK_NUM][len - 1]) });
+        }
+
+        let (block, left) = self.taken.get()?;
+        let value = unsafe { read(&inner.slots[block % BLOCK_NUM][left - 1]) };
+        if left == 1 {
+            self.taken.set(None);
+            inner.next_lap[block % BLOCK_NUM]
+                .store(block.wrapping_add(BLOCK_NUM), Ordering::SeqCst);
+        } else {
+            self.taken.set(Some((block, left - 1)));
+        }
+        Some(value)
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Default for Worker<T, BLOCK_NUM, SLOT_NUM> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Drop for Worker<T, BLOCK_NUM, SLOT_NUM> {
+    fn drop(&mut self) {
+        // The published blocks are left to whoever drops the last handle.
+        while self.pop_local().is_some() {}
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Stealer<T, BLOCK_NUM, SLOT_NUM> {
+    /// Steals the oldest full block.
+    pub fn steal_batch(&self) -> Steal<Batch<'_, T>> {
+        let inner = &*self.inner;
+        let top = inner.top.load(Ordering::SeqCst);
+        let bottom = inner.bottom.load(Ordering::SeqCst);
+
+        if bottom.wrapping_sub(top) as isize <= 0 {
+            return Steal::Empty;
+        }
+        if inner
+            .top
+            .compare_exchange(top, top.wrapping_add(1), Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Steal::Retry;
+        }
+
+        Steal::Success(Batch {
+            slots: &inner.slots[top % BLOCK_NUM],
+            next: 0,
+            next_lap: &inner.next_lap[top % BLOCK_NUM],
+            lap: top.wrapping_add(BLOCK_NUM),
+        })
+    }
+
+    /// Returns `true` if there was no full block to steal at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        let top = self.inner.top.load(Ordering::SeqCst);
+        let bottom = self.inner.bottom.load(Ordering::SeqCst);
+        bottom.wrapping_sub(top) as isize <= 0
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Clone for Stealer<T, BLOCK_NUM, SLOT_NUM> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> Iterator for Batch<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let slot = self.slots.get(self.next)?;
+        self.next += 1;
+        Some(unsafe { read(slot) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.slots.len() - self.next;
+        (left, Some(left))
+    }
+}
+
+impl<T> ExactSizeIterator for Batch<'_, T> {}
+
+impl<T> Drop for Batch<'_, T> {
+    fn drop(&mut self) {
+        for _ in self.by_ref() {}
+        self.next_lap.store(self.lap, Ordering::SeqCst);
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Drop for Inner<T, BLOCK_NUM, SLOT_NUM> {
+    fn drop(&mut self) {
+        let top = *self.top.get_mut();
+        let bottom = *self.bottom.get_mut();
+
+        let mut block = top;
+        while block != bottom {
+            for slot in &self.slots[block % BLOCK_NUM] {
+                drop(unsafe { read(slot) });
+            }
+            block = block.wrapping_add(1);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::bbring::{Block, CACHE_LINE, Interleave, Ring, Slot, one_lap};
+use crate::index::private::Atomic;
+use crate::{ConfigError, DefaultIndex, PopError, PushError, SlotLayout};
+use crossbeam_utils::CachePadded;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem;
+
+/// The number of blocks and slots per block of a ring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geometry {
+    pub block_num: usize,
+    pub slot_num: usize,
+}
+
+/// A [`RingBuffer`](crate::RingBuffer) whose geometry is picked at runtime.
+///
+/// It runs the same protocol, with the same caveats about when a push is refused, but
+/// keeps its blocks and slots on the heap so that the number and size of blocks can come
+/// from configuration instead of the type.
+pub struct DynRingBuffer<T> {
+    head: CachePadded<DefaultIndex>,
+    tail: CachePadded<DefaultIndex>,
+    blocks: Box<[Block<DefaultIndex>]>,
+    slots: Box<[Slot<T>]>,
+
+    slot_num: usize,
+    one_lap: u64,
+    interleave: Interleave,
+}
+
+unsafe impl<T: Send> Send for DynRingBuffer<T> {}
+unsafe impl<T: Send> Sync for DynRingBuffer<T> {}
+
+impl<T> DynRingBuffer<T> {
+    /// Creates a queue of `block_num` blocks with `slot_num` slots each.
+    ///
+    /// Panics if the geometry is rejected by [`try_new`](DynRingBuffer::try_new).
+    pub fn new(block_num: usize, slot_num: usize) -> Self {
+        match Self::try_new(block_num, slot_num) {
+            Ok(this) => this,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Creates a queue of `block_num` blocks with `slot_num` slots each, which must both be
+    /// non-zero. See [`RingBuffer`](crate::RingBuffer) for what other than powers of two
+    /// cost.
+    pub fn try_new(block_num: usize, slot_num: usize) -> Result<Self, ConfigError> {
+        let one_lap = one_lap(block_num, slot_num, DefaultIndex::BITS)?;
+
+        let blocks = (0..block_num)
+            .map(|i| Block::new(one_lap, slot_num, i))
+            .collect();
+        // Slots are `MaybeUninit`, so leaving them uninitialized is fine.
+        let slots = unsafe { Box::new_uninit_slice(block_num * slot_num).assume_init() };
+
+        Ok(Self {
+            head: CachePadded::new(DefaultIndex::new(0)),
+            tail: CachePadded::new(DefaultIndex::new(0)),
+            blocks,
+            slots,
+            slot_num,
+            one_lap,
+            interleave: Interleave::PACKED,
+        })
+    }
+
+    /// Creates a queue of `block_num` blocks with `slot_num` slots each, laid out as
+    /// `layout`. See [`RingBuffer::with_layout`](crate::RingBuffer::with_layout).
+    ///
+    /// Panics if the geometry is rejected by [`try_new`](DynRingBuffer::try_new).
+    pub fn with_layout(block_num: usize, slot_num: usize, layout: SlotLayout) -> Self {
+        let mut this = Self::new(block_num, slot_num);
+        this.interleave = Interleave::new::<T>(layout, slot_num);
+        this
+    }
+
+    /// Creates a queue for `capacity` items shared by about `threads` threads, with the
+    /// geometry from [`Geometry::for_capacity`].
+    ///
+    /// Panics if `capacity` is too large for any geometry, or the one picked is rejected
+    /// by [`try_new`](DynRingBuffer::try_new).
+    pub fn with_capacity(capacity: usize, threads: usize) -> Self {
+        match Geometry::for_capacity::<T>(capacity, threads) {
+            Ok(Geometry {
+                block_num,
+                slot_num,
+            }) => Self::new(block_num, slot_num),
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// See [`RingBuffer::push`](crate::RingBuffer::push).
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        self.ring().push(value)
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        self.try_pop().ok()
+    }
+
+    /// See [`RingBuffer::try_pop`](crate::RingBuffer::try_pop).
+    pub fn try_pop(&self) -> Result<T, PopError> {
+        self.ring().try_pop()
+    }
+
+    /// See [`RingBuffer::close`](crate::RingBuffer::close).
+    pub fn close(&self) -> bool {
+        self.ring().close()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.ring().is_closed()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn block_num(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn slot_num(&self) -> usize {
+        self.slot_num
+    }
+
+    pub fn geometry(&self) -> Geometry {
+        Geometry {
+            block_num: self.blocks.len(),
+            slot_num: self.slot_num,
+        }
+    }
+
+    /// See [`RingBuffer::snapshot`](crate::RingBuffer::snapshot).
+    pub fn snapshot(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.ring().snapshot()
+    }
+
+    #[inline]
+    fn ring(&self) -> Ring<'_, T, DefaultIndex> {
+        Ring {
+            head: &self.head,
+            tail: &self.tail,
+            blocks: &self.blocks,
+            slots: &self.slots,
+            slot_num: self.slot_num,
+            one_lap: self.one_lap,
+            interleave: self.interleave,
+        }
+    }
+}
+
+impl<T> Drop for DynRingBuffer<T> {
+    fn drop(&mut self) {
+        self.ring().drain();
+    }
+}
+
+impl Geometry {
+    /// Picks a geometry for `capacity` items of type `T` shared by about `threads` threads.
+    ///
+    /// Following the BBQ paper, there are enough blocks that producers and consumers
+    /// mostly work in different ones, two per thread and at least four, and every block
+    /// spans whole cache lines of `T`, so that neighbouring blocks do not share one. One
+    /// block more than the capacity needs is added on top, because a push is refused
+    /// while the next block is partly drained: this way `capacity` items always fit.
+    ///
+    /// Fails with [`ConfigError::TooLarge`] if the slots of that geometry do not fit in a
+    /// `usize`.
+    pub fn for_capacity<T>(capacity: usize, threads: usize) -> Result<Self, ConfigError> {
+        let per_line = (CACHE_LINE / mem::size_of::<T>().max(1)).max(1);
+
+        let capacity = capacity.max(1);
+        let blocks = threads.saturating_mul(2).max(4);
+        let slot_num = capacity
+            .div_ceil(blocks)
+            .checked_next_multiple_of(per_line)
+            .ok_or(ConfigError::TooLarge)?;
+        let block_num = capacity
+            .div_ceil(slot_num)
+            .checked_add(1)
+            .ok_or(ConfigError::TooLarge)?;
+        block_num
+            .checked_mul(slot_num)
+            .ok_or(ConfigError::TooLarge)?;
+
+        Ok(Self {
+            block_num,
+            slot_num,
+        })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.block_num * self.slot_num
+    }
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} blocks of {} slots", self.block_num, self.slot_num)
+    }
+}
@@ -0,0 +1,147 @@
+//! A [`RingBuffer`] with a Linux `eventfd` that turns readable when items show up, so a
+//! consumer can wait for it in an epoll or mio event loop.
+//!
+//! The fd is only written when a consumer is waiting: [`EventRing::try_pop`] arms the
+//! notifier when it finds the queue empty, and the next push disarms it and writes the fd.
+//! Pushes into a queue that is already known to be non-empty cost no syscall.
+
+use crate::{PopError, PushError, RingBuffer};
+use crossbeam_utils::CachePadded;
+use mio::unix::SourceFd;
+use mio::{Interest, Registry, Token};
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+
+/// A [`RingBuffer`] paired with an `eventfd` readiness notifier.
+///
+/// A consumer pops until [`try_pop`](EventRing::try_pop) reports [`PopError::Empty`], and
+/// then waits for the fd to turn readable before trying again:
+///
+/// ```no_run
+/// # use bbring::PopError;
+/// # use bbring::eventfd::EventRing;
+/// # use mio::{Events, Poll, Token};
+/// let ring = EventRing::<u64, 8, 64>::new()?;
+/// let mut poll = Poll::new()?;
+/// ring.register(poll.registry(), Token(0))?;
+///
+/// let mut events = Events::with_capacity(16);
+/// loop {
+///     match ring.try_pop() {
+///         Ok(item) => println!("{item}"),
+///         Err(PopError::Empty) => poll.poll(&mut events, None)?,
+///         Err(PopError::Closed) => break,
+///     }
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct EventRing<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    ring: RingBuffer<T, BLOCK_NUM, SLOT_NUM>,
+    fd: OwnedFd,
+    // Set by a consumer that found the queue empty, taken by the push that wakes it.
+    armed: CachePadded<AtomicBool>,
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> EventRing<T, BLOCK_NUM, SLOT_NUM> {
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            ring: RingBuffer::new(),
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            armed: CachePadded::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Pushes `value` and wakes a waiting consumer, see [`RingBuffer::push`].
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        self.ring.push(value)?;
+        self.signal();
+        Ok(())
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        self.try_pop().ok()
+    }
+
+    /// Pops the oldest item, see [`RingBuffer::try_pop`].
+    ///
+    /// On [`PopError::Empty`] the notifier is left armed, so the fd turns readable on the
+    /// next push, or when the queue is closed.
+    pub fn try_pop(&self) -> Result<T, PopError> {
+        match self.ring.try_pop() {
+            Err(PopError::Empty) => {}
+            res => return res,
+        }
+
+        // Arm before looking again, so a push in between still writes the fd.
+        self.arm();
+        self.ring.try_pop()
+    }
+
+    /// Closes the queue and wakes a waiting consumer, see [`RingBuffer::close`].
+    pub fn close(&self) -> bool {
+        let closed = self.ring.close();
+        if closed {
+            self.signal();
+        }
+        closed
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.ring.is_closed()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+
+    /// Registers the eventfd with `registry` for readable events under `token`.
+    pub fn register(&self, registry: &Registry, token: Token) -> io::Result<()> {
+        registry.register(
+            &mut SourceFd(&self.fd.as_raw_fd()),
+            token,
+            Interest::READABLE,
+        )
+    }
+
+    pub fn deregister(&self, registry: &Registry) -> io::Result<()> {
+        registry.deregister(&mut SourceFd(&self.fd.as_raw_fd()))
+    }
+
+    fn arm(&self) {
+        // Reset the counter first, so the fd only turns readable again for a push that
+        // sees us armed. A level-triggered poller would otherwise keep waking up.
+        let mut count = 0u64;
+        unsafe { libc::read(self.fd.as_raw_fd(), (&raw mut count).cast(), 8) };
+        self.armed.store(true, Ordering::SeqCst);
+    }
+
+    fn signal(&self) {
+        // The plain load keeps the cache line shared while nobody is waiting.
+        if self.armed.load(Ordering::SeqCst) && self.armed.swap(false, Ordering::SeqCst) {
+            let one = 1u64;
+            // Only fails if the counter would overflow, and then the fd is readable anyway.
+            unsafe { libc::write(self.fd.as_raw_fd(), (&raw const one).cast(), 8) };
+        }
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> AsFd for EventRing<T, BLOCK_NUM, SLOT_NUM> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> AsRawFd
+    for EventRing<T, BLOCK_NUM, SLOT_NUM>
+{
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
@@ -0,0 +1,265 @@
+//! `Stream` and `Sink` ends for a [`RingBuffer`], so it can be driven with the `futures`
+//! combinators.
+//!
+//! Both ends are cheap to clone and share one ring. A sender that finds the ring full
+//! parks its task until a receiver pops, and a receiver that finds it empty parks until a
+//! sender pushes. The ring refuses pushes while the next block is still being drained, so
+//! a sender can be woken a few times before there is actually room for it.
+
+use crate::{PopError, PushError, RingBuffer, Selectable};
+use ::futures_core::Stream;
+use ::futures_sink::Sink;
+
+use core::mem;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::task::{Context, Poll, Waker};
+use std::sync::{Arc, Mutex};
+
+struct Shared<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    ring: RingBuffer<T, BLOCK_NUM, SLOT_NUM>,
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+
+    // Tasks waiting for room and tasks waiting for items.
+    tx_wakers: WakerList,
+    rx_wakers: WakerList,
+}
+
+/// Creates the two ends of a new ring.
+pub fn channel<T, const BLOCK_NUM: usize, const SLOT_NUM: usize>() -> (
+    AsyncSender<T, BLOCK_NUM, SLOT_NUM>,
+    AsyncReceiver<T, BLOCK_NUM, SLOT_NUM>,
+) {
+    let shared = Arc::new(Shared {
+        ring: RingBuffer::new(),
+        senders: AtomicUsize::new(1),
+        receivers: AtomicUsize::new(1),
+        tx_wakers: WakerList::default(),
+        rx_wakers: WakerList::default(),
+    });
+
+    (
+        AsyncSender {
+            shared: Arc::clone(&shared),
+            pending: None,
+        },
+        AsyncReceiver { shared },
+    )
+}
+
+/// The [`Sink`] end of a [`channel`].
+///
+/// An item that does not fit right away is held back until `poll_ready` or `poll_flush`
+/// manage to push it. Closing the sink closes the ring for every sender, and so does
+/// dropping the last sender.
+pub struct AsyncSender<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    shared: Arc<Shared<T, BLOCK_NUM, SLOT_NUM>>,
+    pending: Option<T>,
+}
+
+/// The [`Stream`] end of a [`channel`], which ends once the ring is closed and drained.
+///
+/// Dropping the last receiver closes the ring, so senders get their items back as
+/// [`PushError::Closed`] instead of waiting for room that never comes.
+pub struct AsyncReceiver<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    shared: Arc<Shared<T, BLOCK_NUM, SLOT_NUM>>,
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Shared<T, BLOCK_NUM, SLOT_NUM> {
+    fn push(&self, value: T) -> Result<(), PushError<T>> {
+        self.ring.push(value)?;
+        self.rx_wakers.wake_all();
+        Ok(())
+    }
+
+    fn try_pop(&self) -> Result<T, PopError> {
+        let value = self.ring.try_pop()?;
+        self.tx_wakers.wake_all();
+        Ok(value)
+    }
+
+    // Wakes both sides, senders parked on a full ring have to see it closed as well.
+    fn close(&self) {
+        if self.ring.close() {
+            self.rx_wakers.wake_all();
+            self.tx_wakers.wake_all();
+        }
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> AsyncSender<T, BLOCK_NUM, SLOT_NUM> {
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), PushError<T>>> {
+        let Some(value) = self.pending.take() else {
+            return Poll::Ready(Ok(()));
+        };
+
+        let value = match self.shared.push(value) {
+            Ok(()) => return Poll::Ready(Ok(())),
+            Err(PushError::Closed(value)) => return Poll::Ready(Err(PushError::Closed(value))),
+            Err(PushError::Full(value)) => value,
+        };
+
+        // Register before trying again, so a pop in between still wakes us.
+        self.shared.tx_wakers.register(cx.waker());
+        match self.shared.push(value) {
+            Ok(()) => Poll::Ready(Ok(())),
+            Err(PushError::Closed(value)) => Poll::Ready(Err(PushError::Closed(value))),
+            Err(PushError::Full(value)) => {
+                self.pending = Some(value);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+// The held back item is only ever moved, never pinned.
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Unpin
+    for AsyncSender<T, BLOCK_NUM, SLOT_NUM>
+{
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Sink<T>
+    for AsyncSender<T, BLOCK_NUM, SLOT_NUM>
+{
+    /// Hands back the item that could not be sent because the ring is closed.
+    type Error = PushError<T>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        debug_assert!(this.pending.is_none(), "start_send without poll_ready");
+
+        match this.shared.push(item) {
+            Ok(()) => Ok(()),
+            Err(PushError::Full(item)) => {
+                this.pending = Some(item);
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_pending(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let res = this.poll_pending(cx);
+        if res.is_ready() {
+            this.shared.close();
+        }
+        res
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Clone
+    for AsyncSender<T, BLOCK_NUM, SLOT_NUM>
+{
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::SeqCst);
+        Self {
+            shared: Arc::clone(&self.shared),
+            pending: None,
+        }
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Drop
+    for AsyncSender<T, BLOCK_NUM, SLOT_NUM>
+{
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shared.close();
+        }
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Stream
+    for AsyncReceiver<T, BLOCK_NUM, SLOT_NUM>
+{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let shared = &self.shared;
+        match shared.try_pop() {
+            Ok(value) => return Poll::Ready(Some(value)),
+            Err(PopError::Closed) => return Poll::Ready(None),
+            Err(PopError::Empty) => {}
+        }
+
+        shared.rx_wakers.register(cx.waker());
+        match shared.try_pop() {
+            Ok(value) => Poll::Ready(Some(value)),
+            Err(PopError::Closed) => Poll::Ready(None),
+            Err(PopError::Empty) => Poll::Pending,
+        }
+    }
+}
+
+// Lets a blocking thread wait on a receiver next to plain rings.
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Selectable<T>
+    for AsyncReceiver<T, BLOCK_NUM, SLOT_NUM>
+{
+    fn try_pop(&self) -> Result<T, PopError> {
+        self.shared.try_pop()
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Clone
+    for AsyncReceiver<T, BLOCK_NUM, SLOT_NUM>
+{
+    fn clone(&self) -> Self {
+        self.shared.receivers.fetch_add(1, Ordering::SeqCst);
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Drop
+    for AsyncReceiver<T, BLOCK_NUM, SLOT_NUM>
+{
+    fn drop(&mut self) {
+        if self.shared.receivers.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shared.close();
+        }
+    }
+}
+
+// Wakers of the parked tasks on one side. The count lets the other side skip the lock
+// while nobody is waiting, which is the common case.
+#[derive(Default)]
+struct WakerList {
+    len: AtomicUsize,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl WakerList {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock().unwrap();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+        self.len.store(wakers.len(), Ordering::SeqCst);
+    }
+
+    fn wake_all(&self) {
+        if self.len.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+
+        let wakers = {
+            let mut wakers = self.wakers.lock().unwrap();
+            self.len.store(0, Ordering::SeqCst);
+            mem::take(&mut *wakers)
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! A hook that runs before every atomic step of the ring protocol.
+//!
+//! This only exists for testing: a scheduler can park the calling thread inside the hook
+//! and pick which thread takes the next step, which turns a concurrent run into a
+//! deterministic interleaving. The hook is per thread.
+//!
+//! With `huge-pages` it can also make huge page mappings fail, to reach the fallback on
+//! hosts where they would succeed.
+
+#[cfg(all(feature = "huge-pages", target_os = "linux"))]
+use std::cell::Cell;
+use std::cell::RefCell;
+
+/// The atomic operation the calling thread is about to perform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Step {
+    PushLoadHead,
+    PopLoadTail,
+
+    AdvanceHeadLoadConsumed,
+    AdvanceHeadLoadReserved,
+    AdvanceHeadInitCommitted,
+    AdvanceHeadInitAllocated,
+    AdvanceHeadPublish,
+
+    AdvanceTailLoadCommitted,
+    AdvanceTailInitConsumed,
+    AdvanceTailInitReserved,
+    AdvanceTailPublish,
+
+    CommitLoadAllocated,
+    CommitAllocate,
+    CommitWrite,
+    // Only taken by `PersistentRing`, which stamps a written item before publishing it.
+    CommitStamp,
+    CommitPublish,
+
+    ConsumeLoadReserved,
+    ConsumeLoadCommitted,
+    ConsumeLoadAllocated,
+    ConsumeReserve,
+    ConsumePublish,
+
+    // `PersistentRing::open` creating a file, before it marks the file as complete.
+    CreateMagic,
+}
+
+type Hook = Box<dyn FnMut(Step)>;
+
+thread_local! {
+    static HOOK: RefCell<Option<Hook>> = const { RefCell::new(None) };
+}
+
+#[cfg(all(feature = "huge-pages", target_os = "linux"))]
+thread_local! {
+    static FAIL_MAPPINGS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Installs `hook` for the calling thread, replacing any previous one.
+pub fn set_step_hook(hook: impl FnMut(Step) + 'static) {
+    HOOK.with(|h| *h.borrow_mut() = Some(Box::new(hook)));
+}
+
+/// Removes the calling thread's hook.
+pub fn clear_step_hook() {
+    HOOK.with(|h| h.borrow_mut().take());
+}
+
+pub(crate) fn step(step: Step) {
+    HOOK.with(|h| {
+        if let Some(hook) = h.borrow_mut().as_mut() {
+            hook(step);
+        }
+    });
+}
+
+/// Makes the mappings [`RingBuffer::new_huge`](crate::RingBuffer::new_huge) tries on the
+/// calling thread fail while `fail` is set, as if neither kind of huge page was available.
+#[cfg(all(feature = "huge-pages", target_os = "linux"))]
+pub fn fail_huge_mappings(fail: bool) {
+    FAIL_MAPPINGS.with(|f| f.set(fail));
+}
+
+#[cfg(all(feature = "huge-pages", target_os = "linux"))]
+pub(crate) fn huge_mappings_fail() -> bool {
+    FAIL_MAPPINGS.with(Cell::get)
+}
@@ -0,0 +1,203 @@
+//! Rings in huge pages on Linux, for queues large enough that TLB misses show.
+//!
+//! [`RingBuffer::new_huge`] asks for explicit `MAP_HUGETLB` pages first, then, unless
+//! transparent huge pages are switched off, for an anonymous mapping advised with
+//! `MADV_HUGEPAGE`. It falls back to the global allocator when neither is available or the
+//! queue does not fill a huge page.
+
+use crate::{Index, RingBuffer, SlotLayout};
+
+use core::fmt;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+use std::fs;
+
+// Used when `/proc/meminfo` can not be read, the default on x86-64.
+const DEFAULT_HUGE_PAGE: usize = 2 << 20;
+
+/// Where the memory of a [`HugeBox`] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backing {
+    /// Explicit huge pages from the `hugetlbfs` pool, which must have been reserved
+    /// through `vm.nr_hugepages`.
+    HugeTlb,
+    /// An anonymous mapping aligned to the huge page size and advised with
+    /// `MADV_HUGEPAGE`, which the kernel backs with transparent huge pages when it can.
+    Transparent,
+    /// The global allocator, as with [`RingBuffer::new_boxed`].
+    Heap,
+}
+
+/// An owned value on the heap, possibly in huge pages, see [`RingBuffer::new_huge`].
+pub struct HugeBox<T> {
+    ptr: NonNull<T>,
+    // Length of the mapping, unused for `Backing::Heap`.
+    len: usize,
+    backing: Backing,
+}
+
+unsafe impl<T: Send> Send for HugeBox<T> {}
+unsafe impl<T: Sync> Sync for HugeBox<T> {}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index>
+    RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>
+{
+    /// Creates the queue on the heap, in huge pages if possible.
+    ///
+    /// A queue smaller than one huge page goes to the global allocator, since a huge page
+    /// would mostly sit unused. [`HugeBox::backing`] tells which memory was used.
+    pub fn new_huge() -> HugeBox<Self> {
+        let size = mem::size_of::<Self>();
+        let huge = huge_page_size();
+
+        let mapped = if size >= huge {
+            let len = size.next_multiple_of(huge);
+            map_hugetlb(len)
+                .map(|ptr| (ptr, len, Backing::HugeTlb))
+                .or_else(|| {
+                    transparent_enabled()
+                        .then(|| map_transparent(len, huge))
+                        .flatten()
+                        .map(|ptr| (ptr, len, Backing::Transparent))
+                })
+        } else {
+            None
+        };
+
+        match mapped {
+            Some((ptr, len, backing)) => {
+                let ptr = ptr.cast::<Self>();
+                // Fresh pages, with the slots left untouched until they are used.
+                unsafe { Self::init(ptr.as_ptr()) };
+                HugeBox { ptr, len, backing }
+            }
+            None => HugeBox {
+                ptr: NonNull::from(Box::leak(Self::new_boxed())),
+                len: 0,
+                backing: Backing::Heap,
+            },
+        }
+    }
+
+    /// Creates the queue like [`new_huge`](RingBuffer::new_huge), with its slots laid out
+    /// as `layout`, see [`with_layout`](RingBuffer::with_layout).
+    pub fn new_huge_with_layout(layout: SlotLayout) -> HugeBox<Self> {
+        let mut this = Self::new_huge();
+        this.set_layout(layout);
+        this
+    }
+}
+
+impl<T> HugeBox<T> {
+    pub fn backing(&self) -> Backing {
+        self.backing
+    }
+}
+
+impl<T> Deref for HugeBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for HugeBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for HugeBox<T> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.backing == Backing::Heap {
+                drop(Box::from_raw(self.ptr.as_ptr()));
+            } else {
+                ptr::drop_in_place(self.ptr.as_ptr());
+                libc::munmap(self.ptr.as_ptr().cast(), self.len);
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for HugeBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+// The default huge page size, which is what `MAP_HUGETLB` without a size flag gets.
+fn huge_page_size() -> usize {
+    fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|meminfo| {
+            let line = meminfo.lines().find(|l| l.starts_with("Hugepagesize:"))?;
+            let kb = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+            Some(kb * 1024)
+        })
+        .unwrap_or(DEFAULT_HUGE_PAGE)
+}
+
+// Whether the kernel may back `MADV_HUGEPAGE` ranges with transparent huge pages. With
+// them set to `never`, or built out, `madvise` still succeeds but nothing comes of it.
+fn transparent_enabled() -> bool {
+    fs::read_to_string("/sys/kernel/mm/transparent_hugepage/enabled")
+        .is_ok_and(|enabled| !enabled.contains("[never]"))
+}
+
+fn mmap(len: usize, flags: libc::c_int) -> Option<NonNull<u8>> {
+    #[cfg(feature = "step-hook")]
+    if crate::hook::huge_mappings_fail() {
+        return None;
+    }
+
+    let ptr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | flags,
+            -1,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        None
+    } else {
+        NonNull::new(ptr.cast())
+    }
+}
+
+// Fails unless enough huge pages are reserved and free.
+fn map_hugetlb(len: usize) -> Option<NonNull<u8>> {
+    mmap(len, libc::MAP_HUGETLB)
+}
+
+// Maps `len` bytes starting on a huge page boundary, since the kernel only backs aligned
+// huge-page-sized ranges with huge pages. Fails only if the mapping itself does, or if
+// the kernel was built without transparent huge pages.
+fn map_transparent(len: usize, huge: usize) -> Option<NonNull<u8>> {
+    let ptr = mmap(len + huge, 0)?;
+    let addr = ptr.as_ptr() as usize;
+    let head = addr.next_multiple_of(huge) - addr;
+    let ptr = unsafe { ptr.add(head) };
+
+    // Give back what is left over on either side of the aligned range.
+    unsafe {
+        if head > 0 {
+            libc::munmap(ptr.sub(head).as_ptr().cast(), head);
+        }
+        let tail = huge - head;
+        if tail > 0 {
+            libc::munmap(ptr.add(len).as_ptr().cast(), tail);
+        }
+    }
+
+    if unsafe { libc::madvise(ptr.as_ptr().cast(), len, libc::MADV_HUGEPAGE) } != 0 {
+        unsafe { libc::munmap(ptr.as_ptr().cast(), len) };
+        return None;
+    }
+    Some(ptr)
+}
@@ -0,0 +1,159 @@
+#[cfg(target_has_atomic = "32")]
+use core::sync::atomic::AtomicU32;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering;
+
+/// The atomic integer a [`RingBuffer`](crate::RingBuffer) keeps its cursors and block
+/// counters in, `AtomicU32` or `AtomicU64`.
+///
+/// With the `portable-atomic` feature the types of the same names from `portable-atomic`
+/// are indexes too, for targets without compare-and-swap, like `thumbv6m` or `riscv32imc`.
+///
+/// A cursor holds a block index in its low bits, the closed flag in its top bit, and in
+/// between the version of the block, which goes up by one every lap and wraps around to
+/// zero once it runs out of bits. Versions are compared within half their range, so a
+/// thread that slept through that many laps could mistake an old lap for the current one.
+/// With 64 bits that takes longer than any process runs. With 32 bits and a ring of 64
+/// blocks of 64 slots, the index takes 7 bits, which leaves 24 for the version, and half
+/// their range is 8 million laps.
+///
+/// A fixed width also gives the ring the same layout on every target, which matters when
+/// it is shared through memory with another process.
+///
+/// A geometry whose block index leaves fewer than 8 bits for the version is rejected when
+/// the type is instantiated:
+///
+/// ```compile_fail
+/// use std::sync::atomic::AtomicU32;
+///
+/// let q = bbring::RingBuffer::<u8, 1, { 1 << 30 }, AtomicU32>::new();
+/// ```
+pub trait Index: private::Atomic {}
+
+/// The [`Index`] rings use unless told otherwise: 64 bits wherever the target has 64-bit
+/// atomics, which leaves at least 32 version bits for any geometry that fits in memory.
+#[cfg(target_has_atomic = "64")]
+pub type DefaultIndex = AtomicU64;
+
+/// The [`Index`] rings use unless told otherwise. This target has no native 64-bit
+/// atomics, so it is the emulated `AtomicU64` of `portable-atomic`, which still leaves at
+/// least 32 version bits.
+#[cfg(not(target_has_atomic = "64"))]
+pub type DefaultIndex = portable_atomic::AtomicU64;
+
+#[cfg(not(any(target_has_atomic = "32", feature = "portable-atomic")))]
+compile_error!(
+    "this target has no atomic read-modify-write operations, enable the `portable-atomic` feature"
+);
+
+#[cfg(target_has_atomic = "32")]
+impl Index for AtomicU32 {}
+#[cfg(target_has_atomic = "64")]
+impl Index for AtomicU64 {}
+#[cfg(any(feature = "portable-atomic", not(target_has_atomic = "64")))]
+impl Index for portable_atomic::AtomicU32 {}
+#[cfg(any(feature = "portable-atomic", not(target_has_atomic = "64")))]
+impl Index for portable_atomic::AtomicU64 {}
+
+// The operations the ring needs, on values widened to `u64`. Kept in a private module so
+// that nothing outside can implement `Index`.
+pub(crate) mod private {
+    use super::*;
+
+    pub trait Atomic: Send + Sync + Sized {
+        const BITS: u32;
+        // Top bit of the head cursor, and of the `allocated` word of a sealed block.
+        const CLOSED: u64 = 1 << (Self::BITS - 1);
+
+        fn new(value: u64) -> Self;
+        fn load(&self, order: Ordering) -> u64;
+        fn store(&self, value: u64, order: Ordering);
+        fn fetch_add(&self, value: u64, order: Ordering) -> u64;
+        fn fetch_or(&self, value: u64, order: Ordering) -> u64;
+        fn compare_exchange(&self, current: u64, new: u64) -> Result<u64, u64>;
+
+        // Wraps a version that was moved past the top, so it never reaches `CLOSED`.
+        #[inline]
+        fn wrap(value: u64) -> u64 {
+            value & (Self::CLOSED - 1)
+        }
+
+        // Whether `a` comes before `b`, for versions or whole cursors. Both are taken to be
+        // within half the wrapped range of each other.
+        #[inline]
+        fn older(a: u64, b: u64) -> bool {
+            let ahead = Self::wrap(b.wrapping_sub(a));
+            ahead != 0 && ahead < Self::CLOSED / 2
+        }
+
+        // Moves the value forward to `value` unless it is there already, or closed, and
+        // returns the previous one. `fetch_max` in the order of `older`.
+        fn fetch_newest(&self, value: u64) -> u64 {
+            let mut current = self.load(Ordering::SeqCst);
+            while current & Self::CLOSED == 0 && Self::older(current, value) {
+                match self.compare_exchange(current, value) {
+                    Ok(_) => break,
+                    Err(actual) => current = actual,
+                }
+            }
+            current
+        }
+    }
+
+    macro_rules! atomic {
+        ($atomic:ty, $int:ty) => {
+            impl Atomic for $atomic {
+                const BITS: u32 = <$int>::BITS;
+
+                fn new(value: u64) -> Self {
+                    <$atomic>::new(value as $int)
+                }
+
+                #[inline]
+                fn load(&self, order: Ordering) -> u64 {
+                    <$atomic>::load(self, order) as u64
+                }
+
+                #[inline]
+                fn store(&self, value: u64, order: Ordering) {
+                    <$atomic>::store(self, value as $int, order)
+                }
+
+                #[inline]
+                fn fetch_add(&self, value: u64, order: Ordering) -> u64 {
+                    <$atomic>::fetch_add(self, value as $int, order) as u64
+                }
+
+                #[inline]
+                fn fetch_or(&self, value: u64, order: Ordering) -> u64 {
+                    <$atomic>::fetch_or(self, value as $int, order) as u64
+                }
+
+                #[inline]
+                fn compare_exchange(&self, current: u64, new: u64) -> Result<u64, u64> {
+                    <$atomic>::compare_exchange(
+                        self,
+                        current as $int,
+                        new as $int,
+                        Ordering::SeqCst,
+                        Ordering::SeqCst,
+                    )
+                    .map(|v| v as u64)
+                    .map_err(|v| v as u64)
+                }
+            }
+        };
+    }
+
+    #[cfg(target_has_atomic = "32")]
+    atomic!(AtomicU32, u32);
+    #[cfg(target_has_atomic = "64")]
+    atomic!(AtomicU64, u64);
+    // Native where the target allows, otherwise emulated with a CAS loop. Targets without
+    // CAS need `portable-atomic` to be told how to make critical sections.
+    #[cfg(any(feature = "portable-atomic", not(target_has_atomic = "64")))]
+    atomic!(portable_atomic::AtomicU32, u32);
+    #[cfg(any(feature = "portable-atomic", not(target_has_atomic = "64")))]
+    atomic!(portable_atomic::AtomicU64, u64);
+}
@@ -1,10 +1,67 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+// Marks a point where the step hook may interleave another thread.
+macro_rules! step {
+    ($step:ident) => {
+        #[cfg(feature = "step-hook")]
+        $crate::hook::step($crate::hook::Step::$step);
+    };
+}
+
 mod bbring;
+#[cfg(feature = "std")]
+mod broadcast;
+#[cfg(feature = "std")]
+mod deque;
+mod dynamic;
+#[cfg(all(feature = "eventfd", target_os = "linux"))]
+pub mod eventfd;
+#[cfg(feature = "futures")]
+pub mod futures;
+#[cfg(feature = "step-hook")]
+pub mod hook;
+#[cfg(all(feature = "huge-pages", target_os = "linux"))]
+pub mod huge;
+mod index;
+#[cfg(all(feature = "numa", target_os = "linux"))]
+pub mod numa;
+#[cfg(all(feature = "persist", unix, target_has_atomic = "64"))]
+pub mod persist;
+mod pool;
+#[cfg(feature = "std")]
+mod priority;
+#[cfg(feature = "std")]
+mod select;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "std")]
+mod sharded;
+#[cfg(feature = "tokio")]
+pub mod tokio;
 
 pub use bbring::*;
+#[cfg(feature = "std")]
+pub use broadcast::*;
+#[cfg(feature = "std")]
+pub use deque::*;
+pub use dynamic::*;
+pub use index::*;
+pub use pool::*;
+#[cfg(feature = "std")]
+pub use priority::*;
+#[cfg(feature = "std")]
+pub use select::*;
+#[cfg(feature = "std")]
+pub use sharded::*;
 
 #[cfg(test)]
 mod tests {
-    use crate::RingBuffer;
+    use crate::{PopError, PushError, RingBuffer};
+
+    use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+    use std::thread;
 
     #[test]
     fn simple_push() {
@@ -27,4 +84,57 @@ mod tests {
         let r2 = bbring.pop().unwrap();
         assert_eq!(r2, String::from("world"));
     }
+
+    #[test]
+    fn version_wrap() {
+        // One lap moves the version by 4, start 8 laps before it runs out of bits.
+        type Q = RingBuffer<usize, 2, 2, AtomicU32>;
+        let top = (1 << 31) - 8 * 4;
+
+        let q = Q::starting_at(top);
+        for lap in 0..32 {
+            for i in 0..4 {
+                q.push(lap * 4 + i).unwrap();
+            }
+            assert_eq!(q.push(0), Err(PushError::Full(0)));
+            for i in 0..4 {
+                assert_eq!(q.pop(), Some(lap * 4 + i));
+            }
+            assert_eq!(q.pop(), None);
+            assert!(!q.is_closed());
+        }
+        q.push(1).unwrap();
+        assert!(q.close());
+        assert_eq!(q.push(2), Err(PushError::Closed(2)));
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.try_pop(), Err(PopError::Closed));
+
+        // Producers and consumers running over the wrap with items in flight.
+        const COUNT: usize = if cfg!(miri) { 50 } else { 10_000 };
+        let q = Q::starting_at(top);
+        let v = (0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+        thread::scope(|scope| {
+            for _ in 0..2 {
+                scope.spawn(|| {
+                    for _ in 0..COUNT {
+                        let n = loop {
+                            if let Some(x) = q.pop() {
+                                break x;
+                            }
+                        };
+                        v[n].fetch_add(1, Ordering::SeqCst);
+                    }
+                });
+                scope.spawn(|| {
+                    for i in 0..COUNT {
+                        while q.push(i).is_err() {}
+                    }
+                });
+            }
+        });
+        assert!(!q.is_closed());
+        for c in v {
+            assert_eq!(c.load(Ordering::SeqCst), 2);
+        }
+    }
 }
@@ -0,0 +1,183 @@
+//! NUMA placement for rings on Linux.
+//!
+//! Memory is placed with the raw `mbind` and `getcpu` syscalls, so libnuma is not needed.
+//! Where the kernel has no NUMA support, or a container's seccomp profile refuses the
+//! calls, everything here still works and just leaves placement to the kernel.
+
+use crate::{Index, PopError, PushError, RingBuffer, ShardedRing};
+
+use core::mem;
+use core::ptr;
+use std::fs;
+use std::io;
+
+// From `linux/mempolicy.h`, which the libc crate does not cover.
+const MPOL_PREFERRED: libc::c_int = 1;
+const MPOL_MF_MOVE: libc::c_uint = 1 << 1;
+
+// Nodes a policy can name, as many as the largest kernel configs support.
+const MAX_NODES: usize = 1024;
+
+/// Number of NUMA nodes, counting from node 0 up to the highest one online.
+///
+/// One when the system does not expose its nodes, as on single-node kernels without NUMA
+/// support or with `/sys` not mounted.
+pub fn node_count() -> usize {
+    fs::read_to_string("/sys/devices/system/node/online")
+        .ok()
+        .and_then(|online| parse_highest(&online))
+        .map_or(1, |highest| highest + 1)
+}
+
+/// The node the calling thread is running on, zero if the kernel will not say.
+///
+/// Threads can migrate, so this may be stale by the time it is used.
+pub fn current_node() -> usize {
+    let mut node: libc::c_uint = 0;
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_getcpu,
+            ptr::null_mut::<libc::c_uint>(),
+            &raw mut node,
+            ptr::null_mut::<libc::c_void>(),
+        )
+    };
+    if res == 0 { node as usize } else { 0 }
+}
+
+// The highest node in a list like `0-1,4`.
+fn parse_highest(list: &str) -> Option<usize> {
+    list.trim()
+        .split(',')
+        .filter_map(|range| range.rsplit('-').next()?.parse().ok())
+        .max()
+}
+
+// Asks the kernel to place the pages lying wholly within `ptr..ptr + len` on `node`,
+// moving those that are already there.
+//
+// The policy is only a preference: allocator pages can be handed out again after the
+// queue is freed, and a strict binding would follow them to unrelated allocations.
+fn prefer_node(ptr: *const u8, len: usize, node: usize) -> io::Result<()> {
+    if node >= MAX_NODES {
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let start = (ptr as usize).next_multiple_of(page);
+    let end = (ptr as usize + len) / page * page;
+    if start >= end {
+        return Ok(());
+    }
+
+    let bits = libc::c_ulong::BITS as usize;
+    let mut mask = [0 as libc::c_ulong; MAX_NODES / libc::c_ulong::BITS as usize];
+    mask[node / bits] |= 1 << (node % bits);
+
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_mbind,
+            ptr.with_addr(start),
+            end - start,
+            MPOL_PREFERRED,
+            mask.as_ptr(),
+            // The kernel reads one bit less than it is told.
+            MAX_NODES + 1,
+            MPOL_MF_MOVE,
+        )
+    };
+    if res == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index>
+    RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>
+{
+    /// Creates the queue on the heap with its memory placed on NUMA node `node`.
+    ///
+    /// Placement is best effort: if the node does not exist or the kernel refuses, the
+    /// queue is returned all the same, placed as [`new_boxed`](RingBuffer::new_boxed)
+    /// would. Only whole pages can be placed, so the first and last page of the
+    /// allocation may stay where the allocator put them.
+    pub fn new_boxed_on_node(node: usize) -> Box<Self> {
+        let mut this = Box::<Self>::new_uninit();
+        // Before `init`, so even the counters are first touched on the right node.
+        let _ = prefer_node(this.as_ptr().cast(), mem::size_of::<Self>(), node);
+        unsafe {
+            Self::init(this.as_mut_ptr());
+            this.assume_init()
+        }
+    }
+}
+
+/// A queue with one [`RingBuffer`] shard per NUMA node, each placed on its own node.
+///
+/// Producers push into the shard of the node they run on, and consumers pop from their
+/// own node's shard first and only then look at the others. Ordering is relaxed as in
+/// [`ShardedRing`]: items from one thread stay in order as long as it is not moved to
+/// another node. On a single-node machine this is a plain `RingBuffer`.
+///
+/// The shards sit next to each other like those of a `ShardedRing`, so the pages they
+/// share at their edges stay where the allocator put them.
+pub struct NumaRing<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    ring: ShardedRing<T, BLOCK_NUM, SLOT_NUM>,
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> NumaRing<T, BLOCK_NUM, SLOT_NUM> {
+    /// Creates one shard for every node from [`node_count`].
+    pub fn new() -> Self {
+        let ring = ShardedRing::with_placement(node_count(), |node, shard| {
+            // Before `init`, so even the counters are first touched on the right node.
+            let _ = prefer_node(
+                shard.cast(),
+                mem::size_of::<RingBuffer<T, BLOCK_NUM, SLOT_NUM>>(),
+                node,
+            );
+        });
+        Self { ring }
+    }
+
+    /// Pushes `value` into the shard of the calling thread's node.
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        self.ring.push_to(current_node(), value)
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        self.try_pop().ok()
+    }
+
+    /// Pops from the local shard, or else from the first other shard that has something.
+    ///
+    /// Reports [`PopError::Closed`] only once every shard is closed and drained.
+    pub fn try_pop(&self) -> Result<T, PopError> {
+        let start = current_node() % self.ring.shards();
+        self.ring.try_pop_from(start).map(|(value, _)| value)
+    }
+
+    /// Closes every shard, see [`RingBuffer::close`].
+    pub fn close(&self) -> bool {
+        self.ring.close()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.ring.is_closed()
+    }
+
+    pub fn shards(&self) -> usize {
+        self.ring.shards()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Default
+    for NumaRing<T, BLOCK_NUM, SLOT_NUM>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
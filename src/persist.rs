@@ -0,0 +1,241 @@
+//! A [`RingBuffer`] kept in a memory-mapped file, so its contents survive the process.
+//!
+//! The whole ring lives in the mapping, cursors and block counters included, behind a
+//! header that records the geometry. The counters are `AtomicU64` whatever the target's
+//! [`DefaultIndex`](crate::DefaultIndex). Reopening the file after a crash repairs
+//! whatever the dead process left half done, see [`PersistentRing::open`].
+
+use crate::{PopError, PushError, RingBuffer};
+
+use core::mem::{self, MaybeUninit};
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{self, AtomicU64, Ordering};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+const MAGIC: [u8; 8] = *b"BBRING\0\x01";
+
+#[repr(C)]
+#[derive(PartialEq, Eq)]
+struct Header {
+    // Written last when a file is created, so a file that never got it is started over.
+    magic: [u8; 8],
+    block_num: u64,
+    slot_num: u64,
+    item_size: u64,
+    item_align: u64,
+    ring_size: u64,
+}
+
+// What a slot holds. The stamp is the version of the block the item was pushed into,
+// plus one so that the zeroed slots of a new file never match, or zero for a slot a push
+// was cut off from. It is stored once the value is in the slot, so a push cut off while
+// copying the value never leaves a matching stamp.
+#[repr(C)]
+struct Stamped<T> {
+    stamp: AtomicU64,
+    value: MaybeUninit<T>,
+}
+
+#[repr(C)]
+struct Mapped<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    header: Header,
+    ring: RingBuffer<Stamped<T>, BLOCK_NUM, SLOT_NUM, AtomicU64>,
+}
+
+/// A [`RingBuffer`] for `Copy` items that lives in a memory-mapped file.
+///
+/// Items are stored as raw bytes, so they must not hold pointers or anything else that
+/// only makes sense inside one process. The file is locked while it is open, and everything
+/// in it survives the process being killed at any point; surviving power loss takes
+/// [`flush`](PersistentRing::flush) as well. Only files written by the same build of this
+/// crate with the same `T` and geometry can be opened.
+pub struct PersistentRing<T: Copy + 'static, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    mapped: NonNull<Mapped<T, BLOCK_NUM, SLOT_NUM>>,
+    // Keeps the lock.
+    _file: File,
+    cut_off: usize,
+}
+
+unsafe impl<T: Copy + Send + 'static, const BLOCK_NUM: usize, const SLOT_NUM: usize> Send
+    for PersistentRing<T, BLOCK_NUM, SLOT_NUM>
+{
+}
+unsafe impl<T: Copy + Send + 'static, const BLOCK_NUM: usize, const SLOT_NUM: usize> Sync
+    for PersistentRing<T, BLOCK_NUM, SLOT_NUM>
+{
+}
+
+impl<T: Copy + 'static, const BLOCK_NUM: usize, const SLOT_NUM: usize>
+    PersistentRing<T, BLOCK_NUM, SLOT_NUM>
+{
+    /// Opens the ring in the file at `path`, creating an empty one if the file does not
+    /// exist or is empty.
+    ///
+    /// An existing ring is checked and repaired first. Pushes that a crash cut off after
+    /// they took a slot are rolled back, and counted in
+    /// [`cut_off`](PersistentRing::cut_off); a push that finished writing its item
+    /// may be kept even though it never returned. The item of a pop that was cut off is
+    /// lost.
+    ///
+    /// Fails with [`io::ErrorKind::WouldBlock`] if the file is open elsewhere, and with
+    /// [`io::ErrorKind::InvalidData`] if it holds something else.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let len = mem::size_of::<Mapped<T, BLOCK_NUM, SLOT_NUM>>();
+        let file_len = file.metadata()?.len();
+        if file_len == 0 {
+            file.set_len(len as u64)?;
+        } else if file_len != len as u64 {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        let mut this = Self {
+            mapped: NonNull::new(ptr.cast()).unwrap(),
+            _file: file,
+            cut_off: 0,
+        };
+
+        let mapped = this.mapped.as_ptr();
+        let header = unsafe { &mut (*mapped).header };
+        if header.magic == [0; 8] {
+            unsafe { RingBuffer::init(&raw mut (*mapped).ring) };
+            *header = Header {
+                magic: [0; 8],
+                ..Self::header()
+            };
+            // Everything else has to be in the file before the magic says it is complete.
+            atomic::fence(Ordering::SeqCst);
+            step!(CreateMagic);
+            header.magic = MAGIC;
+        } else if *header != Self::header() {
+            return Err(io::ErrorKind::InvalidData.into());
+        } else {
+            this.cut_off = unsafe { this.recover() };
+        }
+        Ok(this)
+    }
+
+    /// Pushes `value` at the head, see [`RingBuffer::push`].
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        let value = Stamped {
+            stamp: AtomicU64::new(0),
+            value: MaybeUninit::new(value),
+        };
+        self.inner()
+            .ring()
+            .push_stamped(value, |item, vsn| {
+                step!(CommitStamp);
+                item.stamp.store(vsn + 1, Ordering::Release);
+            })
+            .map_err(|err| match err {
+                PushError::Full(item) => PushError::Full(unsafe { item.value.assume_init() }),
+                PushError::Closed(item) => PushError::Closed(unsafe { item.value.assume_init() }),
+            })
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        self.try_pop().ok()
+    }
+
+    /// Pops the oldest item, see [`RingBuffer::try_pop`].
+    pub fn try_pop(&self) -> Result<T, PopError> {
+        loop {
+            let Stamped { stamp, value } = self.inner().try_pop()?;
+            // Skip the holes left by pushes that were cut off.
+            if stamp.into_inner() != 0 {
+                return Ok(unsafe { value.assume_init() });
+            }
+        }
+    }
+
+    /// Closes the queue for good, see [`RingBuffer::close`].
+    pub fn close(&self) -> bool {
+        self.inner().close()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner().is_closed()
+    }
+
+    pub fn capacity(&self) -> usize {
+        BLOCK_NUM * SLOT_NUM
+    }
+
+    /// Number of pushes that were rolled back when the file was opened.
+    pub fn cut_off(&self) -> usize {
+        self.cut_off
+    }
+
+    /// Writes the mapping back to the file and waits for it to reach the disk.
+    pub fn flush(&self) -> io::Result<()> {
+        let len = mem::size_of::<Mapped<T, BLOCK_NUM, SLOT_NUM>>();
+        if unsafe { libc::msync(self.mapped.as_ptr().cast(), len, libc::MS_SYNC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn inner(&self) -> &RingBuffer<Stamped<T>, BLOCK_NUM, SLOT_NUM, AtomicU64> {
+        unsafe { &self.mapped.as_ref().ring }
+    }
+
+    fn header() -> Header {
+        Header {
+            magic: MAGIC,
+            block_num: BLOCK_NUM as u64,
+            slot_num: SLOT_NUM as u64,
+            item_size: mem::size_of::<T>() as u64,
+            item_align: mem::align_of::<T>() as u64,
+            ring_size: mem::size_of::<RingBuffer<Stamped<T>, BLOCK_NUM, SLOT_NUM, AtomicU64>>()
+                as u64,
+        }
+    }
+
+    // Safety: the file must be locked, so nothing else uses the ring.
+    unsafe fn recover(&self) -> usize {
+        let seal = |item: *mut Stamped<T>, vsn: u64| {
+            let stamp = unsafe { &(*item).stamp };
+            if stamp.load(Ordering::Acquire) == vsn + 1 {
+                return true;
+            }
+            stamp.store(0, Ordering::Relaxed);
+            false
+        };
+        unsafe { self.inner().ring().recover(seal) }
+    }
+}
+
+impl<T: Copy + 'static, const BLOCK_NUM: usize, const SLOT_NUM: usize> Drop
+    for PersistentRing<T, BLOCK_NUM, SLOT_NUM>
+{
+    fn drop(&mut self) {
+        // The items stay in the file, so the ring is not dropped, only unmapped.
+        let len = mem::size_of::<Mapped<T, BLOCK_NUM, SLOT_NUM>>();
+        unsafe { libc::munmap(self.mapped.as_ptr().cast(), len) };
+    }
+}
@@ -0,0 +1,108 @@
+use crate::DynRingBuffer;
+
+use alloc::boxed::Box;
+use core::fmt;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+
+type Create<T> = Box<dyn Fn() -> T + Send + Sync>;
+type Reset<T> = Box<dyn Fn(&mut T) + Send + Sync>;
+
+/// A pool of reusable objects, with the free ones kept in a [`DynRingBuffer`].
+///
+/// [`get`](ObjectPool::get) pops a free object, or creates a new one when the ring comes
+/// up empty. The [`Pooled`] guard it returns pushes the object back when dropped, after
+/// running the reset hook if there is one. An object that does not fit back, because the
+/// ring is full, is simply dropped, so the pool never holds more than its capacity.
+pub struct ObjectPool<T> {
+    free: DynRingBuffer<T>,
+    create: Create<T>,
+    reset: Option<Reset<T>>,
+}
+
+/// An object borrowed from an [`ObjectPool`], returned to it on drop.
+pub struct Pooled<'a, T> {
+    pool: &'a ObjectPool<T>,
+    value: ManuallyDrop<T>,
+}
+
+impl<T> ObjectPool<T> {
+    /// Creates an empty pool that keeps up to `block_num * slot_num` free objects and makes
+    /// new ones with `create`.
+    ///
+    /// Panics if either is zero.
+    pub fn new(
+        block_num: usize,
+        slot_num: usize,
+        create: impl Fn() -> T + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            free: DynRingBuffer::new(block_num, slot_num),
+            create: Box::new(create),
+            reset: None,
+        }
+    }
+
+    /// Runs `reset` on every object handed back, before it is stored for reuse.
+    pub fn with_reset(mut self, reset: impl Fn(&mut T) + Send + Sync + 'static) -> Self {
+        self.reset = Some(Box::new(reset));
+        self
+    }
+
+    /// Takes a free object, or creates one if there is none.
+    pub fn get(&self) -> Pooled<'_, T> {
+        let value = self.free.pop().unwrap_or_else(|| (self.create)());
+        Pooled {
+            pool: self,
+            value: ManuallyDrop::new(value),
+        }
+    }
+
+    /// Maximum number of free objects kept.
+    pub fn capacity(&self) -> usize {
+        self.free.capacity()
+    }
+
+    fn put(&self, mut value: T) {
+        if let Some(reset) = &self.reset {
+            reset(&mut value);
+        }
+        // Full, or a block still draining: let this one go.
+        let _ = self.free.push(value);
+    }
+}
+
+impl<T> Pooled<'_, T> {
+    /// Takes the object out of the pool for good.
+    pub fn into_inner(this: Self) -> T {
+        let mut this = ManuallyDrop::new(this);
+        unsafe { ManuallyDrop::take(&mut this.value) }
+    }
+}
+
+impl<T> Deref for Pooled<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Pooled<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> Drop for Pooled<'_, T> {
+    fn drop(&mut self) {
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        self.pool.put(value);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Pooled<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{PushError, RingBuffer};
+use crossbeam_utils::CachePadded;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A set of `RingBuffer`s, one per priority level, with FIFO order inside each level.
+///
+/// Level `LEVELS - 1` is the most urgent one. `pop` reads a summary bitmap of the non-empty
+/// levels to find the highest one without probing every ring.
+pub struct PriorityRing<T, const LEVELS: usize, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    rings: [RingBuffer<T, BLOCK_NUM, SLOT_NUM>; LEVELS],
+    // Bit `i` is set while level `i` may hold items. It is set after every push and only
+    // cleared by a consumer that then checks the level once more.
+    summary: CachePadded<AtomicUsize>,
+
+    // Top level pops in a row while a lower level was waiting.
+    streak: CachePadded<AtomicUsize>,
+    // Level the guard served last, `LEVELS` before it first fires.
+    guarded: AtomicUsize,
+    guard: Option<usize>,
+}
+
+impl<T, const LEVELS: usize, const BLOCK_NUM: usize, const SLOT_NUM: usize>
+    PriorityRing<T, LEVELS, BLOCK_NUM, SLOT_NUM>
+{
+    pub fn new() -> Self {
+        if LEVELS == 0 || LEVELS > usize::BITS as usize {
+            panic!("levels must fit in the summary bitmap")
+        }
+
+        Self {
+            rings: core::array::from_fn(|_| RingBuffer::new()),
+            summary: CachePadded::new(AtomicUsize::new(0)),
+            streak: CachePadded::new(AtomicUsize::new(0)),
+            guarded: AtomicUsize::new(LEVELS),
+            guard: None,
+        }
+    }
+
+    /// Creates a queue that hands out one item from a lower level after every `every`
+    /// pops served from the top level while that lower level was waiting.
+    ///
+    /// The waiting lower levels take turns, from the higher ones down, so with `n` of
+    /// them each one is served at least once every `n` times the guard fires.
+    ///
+    /// The streak is counted with relaxed bookkeeping, so under contention a lower level
+    /// may be served a few pops late, but it can not be starved.
+    pub fn with_starvation_guard(every: usize) -> Self {
+        if every == 0 {
+            panic!("starvation guard period must be non-zero")
+        }
+
+        Self {
+            guard: Some(every),
+            ..Self::new()
+        }
+    }
+
+    /// Pushes `value` at priority `level`.
+    ///
+    /// Panics if `level` is not below `LEVELS`.
+    pub fn push(&self, level: usize, value: T) -> Result<(), PushError<T>> {
+        self.rings[level].push(value)?;
+        self.summary.fetch_or(1 << level, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Pops the oldest item of the highest non-empty level.
+    pub fn pop(&self) -> Option<T> {
+        loop {
+            let summary = self.summary.load(Ordering::SeqCst);
+            if summary == 0 {
+                return None;
+            }
+
+            let top = highest_level(summary);
+            let lower = summary & levels_below(top);
+
+            if lower != 0
+                && let Some(every) = self.guard
+                && self.streak.load(Ordering::Relaxed) >= every
+            {
+                // Go on below the level served last time, or start over from the top.
+                let below = lower & levels_below(self.guarded.load(Ordering::Relaxed));
+                let level = highest_level(if below != 0 { below } else { lower });
+                if let Some(value) = self.pop_level(level) {
+                    self.streak.store(0, Ordering::Relaxed);
+                    self.guarded.store(level, Ordering::Relaxed);
+                    return Some(value);
+                }
+                continue;
+            }
+
+            if let Some(value) = self.pop_level(top) {
+                if lower != 0 && self.guard.is_some() {
+                    self.streak.fetch_add(1, Ordering::Relaxed);
+                }
+                return Some(value);
+            }
+        }
+    }
+
+    /// Returns `true` if no level is marked as non-empty. A level drained by the last pop
+    /// stays marked until a later `pop` finds it empty.
+    pub fn is_empty(&self) -> bool {
+        self.summary.load(Ordering::SeqCst) == 0
+    }
+
+    /// Capacity of every single level.
+    pub fn level_capacity(&self) -> usize {
+        BLOCK_NUM * SLOT_NUM
+    }
+
+    fn pop_level(&self, level: usize) -> Option<T> {
+        let bit = 1 << level;
+
+        if let Some(value) = self.rings[level].pop() {
+            return Some(value);
+        }
+
+        // The level looks empty. Clear its bit and look once more, a push that finished
+        // before the clear is seen now, and one that finishes after it sets the bit again.
+        self.summary.fetch_and(!bit, Ordering::SeqCst);
+        let value = self.rings[level].pop();
+        if value.is_some() {
+            self.summary.fetch_or(bit, Ordering::SeqCst);
+        }
+        value
+    }
+}
+
+impl<T, const LEVELS: usize, const BLOCK_NUM: usize, const SLOT_NUM: usize> Default
+    for PriorityRing<T, LEVELS, BLOCK_NUM, SLOT_NUM>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn highest_level(bits: usize) -> usize {
+    (usize::BITS - 1 - bits.leading_zeros()) as usize
+}
+
+// Bits of the levels under `level`, all of them for `usize::BITS`.
+fn levels_below(level: usize) -> usize {
+    1usize
+        .checked_shl(level as u32)
+        .map_or(usize::MAX, |bit| bit - 1)
+}
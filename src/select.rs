@@ -0,0 +1,164 @@
+use crate::{DynRingBuffer, Index, PopError, RingBuffer, ShardedRing};
+use crossbeam_utils::Backoff;
+
+use core::fmt;
+use core::time::Duration;
+use std::thread;
+use std::time::Instant;
+
+// Longest nap between two rounds once spinning is done. Rings have no wait list, so this
+// bounds how late a blocked select notices an item.
+const MAX_SLEEP: Duration = Duration::from_millis(1);
+
+/// A queue that a [`Select`] can wait on.
+///
+/// Readiness is whatever `try_pop` reports: an item, [`PopError::Empty`] for "not yet",
+/// or [`PopError::Closed`] once the queue is closed and drained for good.
+pub trait Selectable<T> {
+    fn try_pop(&self) -> Result<T, PopError>;
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index> Selectable<T>
+    for RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>
+{
+    fn try_pop(&self) -> Result<T, PopError> {
+        RingBuffer::try_pop(self)
+    }
+}
+
+impl<T> Selectable<T> for DynRingBuffer<T> {
+    fn try_pop(&self) -> Result<T, PopError> {
+        DynRingBuffer::try_pop(self)
+    }
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> Selectable<T>
+    for ShardedRing<T, BLOCK_NUM, SLOT_NUM>
+{
+    fn try_pop(&self) -> Result<T, PopError> {
+        ShardedRing::try_pop(self)
+    }
+}
+
+/// Waits on several queues at once and pops from whichever has an item first.
+///
+/// Queues are registered with [`recv`](Select::recv), which hands out the index that the
+/// select operations report next to the value. Every round starts after the queue that
+/// was served last, so a busy queue can not starve the others.
+///
+/// Blocking spins for a while and then sleeps in growing naps of up to a millisecond,
+/// since the rings have nobody to wake a waiter.
+pub struct Select<'a, T> {
+    queues: Vec<&'a dyn Selectable<T>>,
+    // Where the next round starts.
+    next: usize,
+}
+
+/// Error returned by [`Select::select_timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectTimeoutError {
+    /// No queue had an item before the timeout.
+    Timeout,
+    /// Every queue is closed and drained.
+    Closed,
+}
+
+impl<'a, T> Select<'a, T> {
+    pub fn new() -> Self {
+        Self {
+            queues: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Adds `queue` to the set and returns its index.
+    pub fn recv(&mut self, queue: &'a dyn Selectable<T>) -> usize {
+        self.queues.push(queue);
+        self.queues.len() - 1
+    }
+
+    /// Pops from the first queue that has an item right now.
+    ///
+    /// Reports [`PopError::Closed`] only once every queue is closed and drained, which
+    /// includes the case of an empty set.
+    pub fn try_select(&mut self) -> Result<(usize, T), PopError> {
+        let n = self.queues.len();
+        let mut closed = 0;
+
+        for i in 0..n {
+            let idx = (self.next + i) % n;
+            match self.queues[idx].try_pop() {
+                Ok(value) => {
+                    self.next = idx + 1;
+                    return Ok((idx, value));
+                }
+                Err(PopError::Closed) => closed += 1,
+                Err(PopError::Empty) => {}
+            }
+        }
+
+        if closed == n {
+            Err(PopError::Closed)
+        } else {
+            Err(PopError::Empty)
+        }
+    }
+
+    /// Blocks until some queue has an item, or returns `None` once every queue is closed
+    /// and drained.
+    pub fn select(&mut self) -> Option<(usize, T)> {
+        self.wait(None).ok()
+    }
+
+    /// Like [`select`](Select::select), but gives up after `timeout`.
+    pub fn select_timeout(&mut self, timeout: Duration) -> Result<(usize, T), SelectTimeoutError> {
+        self.wait(Instant::now().checked_add(timeout))
+    }
+
+    fn wait(&mut self, deadline: Option<Instant>) -> Result<(usize, T), SelectTimeoutError> {
+        let backoff = Backoff::new();
+        let mut sleep = Duration::from_micros(1);
+
+        loop {
+            match self.try_select() {
+                Ok(ready) => return Ok(ready),
+                Err(PopError::Closed) => return Err(SelectTimeoutError::Closed),
+                Err(PopError::Empty) => {}
+            }
+
+            let now = Instant::now();
+            if deadline.is_some_and(|d| now >= d) {
+                return Err(SelectTimeoutError::Timeout);
+            }
+
+            if !backoff.is_completed() {
+                backoff.snooze();
+                continue;
+            }
+
+            let nap = match deadline {
+                Some(d) => sleep.min(d - now),
+                None => sleep,
+            };
+            thread::sleep(nap);
+            sleep = (sleep * 2).min(MAX_SLEEP);
+        }
+    }
+}
+
+impl<T> Default for Select<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for SelectTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectTimeoutError::Timeout => f.write_str("timed out waiting on select"),
+            SelectTimeoutError::Closed => f.write_str("select over closed and drained queues"),
+        }
+    }
+}
+
+impl std::error::Error for SelectTimeoutError {}
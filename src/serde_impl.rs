@@ -0,0 +1,74 @@
+use crate::{Index, RingBuffer};
+
+use core::fmt;
+use core::marker::PhantomData;
+use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+// Only the logical FIFO contents are written, the block counters are rebuilt on load.
+// Like `snapshot`, serializing reads the slots in place and expects a quiescent queue.
+impl<T: Serialize, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index> Serialize
+    for RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut len = 0;
+        self.ring().for_each_queued(|_| len += 1);
+
+        let mut seq = serializer.serialize_seq(Some(len))?;
+        let mut res = Ok(());
+        self.ring().for_each_queued(|item| {
+            if res.is_ok() {
+                res = seq.serialize_element(item);
+            }
+        });
+        res?;
+        seq.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index>
+    Deserialize<'de> for RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(RingBufferVisitor(PhantomData))
+    }
+}
+
+struct RingBufferVisitor<T, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index>(
+    PhantomData<RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>>,
+);
+
+impl<'de, T: Deserialize<'de>, const BLOCK_NUM: usize, const SLOT_NUM: usize, I: Index> Visitor<'de>
+    for RingBufferVisitor<T, BLOCK_NUM, SLOT_NUM, I>
+{
+    type Value = RingBuffer<T, BLOCK_NUM, SLOT_NUM, I>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "a sequence of at most {} elements",
+            BLOCK_NUM * SLOT_NUM
+        )
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let ring = RingBuffer::new();
+
+        if let Some(len) = seq.size_hint()
+            && len > ring.capacity()
+        {
+            return Err(A::Error::invalid_length(len, &self));
+        }
+
+        // A fresh buffer accepts exactly `capacity()` pushes before it reports full.
+        let mut len = 0;
+        while let Some(item) = seq.next_element()? {
+            if ring.push(item).is_err() {
+                return Err(A::Error::invalid_length(len + 1, &self));
+            }
+            len += 1;
+        }
+
+        Ok(ring)
+    }
+}
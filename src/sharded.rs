@@ -0,0 +1,123 @@
+use crate::{PopError, PushError, RingBuffer};
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_SHARD_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // Handed out once per thread and shared by every `ShardedRing`.
+    static SHARD_ID: usize = NEXT_SHARD_ID.fetch_add(1, Ordering::Relaxed);
+    // Where this thread starts its next round of stealing.
+    static STEAL_FROM: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A queue spread over several `RingBuffer`s so that producers on different threads do not
+/// all contend on one `head`.
+///
+/// Every producer thread pushes into the shard picked by its thread-local shard id, and
+/// consumers go round-robin over all shards. This relaxes FIFO order:
+///
+/// - Items pushed by one thread stay in one shard, so they are popped in the order they
+///   were pushed.
+/// - Items pushed by different threads have no order between them, even if one push
+///   finished before the other started.
+/// - `push` fails when the producer's own shard is full, although other shards may have
+///   room, and `pop` may miss an item pushed into a shard it has already passed.
+pub struct ShardedRing<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> {
+    shards: Box<[RingBuffer<T, BLOCK_NUM, SLOT_NUM>]>,
+}
+
+impl<T, const BLOCK_NUM: usize, const SLOT_NUM: usize> ShardedRing<T, BLOCK_NUM, SLOT_NUM> {
+    pub fn new(shards: usize) -> Self {
+        Self::with_placement(shards, |_, _| {})
+    }
+
+    // Calls `place` with the index and memory of every shard before it is initialized.
+    pub(crate) fn with_placement(
+        shards: usize,
+        mut place: impl FnMut(usize, *mut RingBuffer<T, BLOCK_NUM, SLOT_NUM>),
+    ) -> Self {
+        if shards == 0 {
+            panic!("must have at least one shard")
+        }
+
+        // Built in place, so large shards never pass through the stack.
+        let mut uninit = Box::<[RingBuffer<T, BLOCK_NUM, SLOT_NUM>]>::new_uninit_slice(shards);
+        for (i, shard) in uninit.iter_mut().enumerate() {
+            place(i, shard.as_mut_ptr());
+            unsafe { RingBuffer::init(shard.as_mut_ptr()) };
+        }
+
+        Self {
+            shards: unsafe { uninit.assume_init() },
+        }
+    }
+
+    /// Pushes `value` into the calling thread's shard.
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        self.push_to(self.local_shard(), value)
+    }
+
+    pub(crate) fn push_to(&self, shard: usize, value: T) -> Result<(), PushError<T>> {
+        self.shards[shard % self.shards.len()].push(value)
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        self.try_pop().ok()
+    }
+
+    /// Pops from the first non-empty shard, starting after the one that served this
+    /// thread last time.
+    ///
+    /// Reports [`PopError::Closed`] only once every shard is closed and drained.
+    pub fn try_pop(&self) -> Result<T, PopError> {
+        let (value, idx) = self.try_pop_from(STEAL_FROM.with(Cell::get))?;
+        STEAL_FROM.with(|s| s.set(idx + 1));
+        Ok(value)
+    }
+
+    // Pops from the first non-empty shard from `start` on, and tells which one it was.
+    pub(crate) fn try_pop_from(&self, start: usize) -> Result<(T, usize), PopError> {
+        let n = self.shards.len();
+        let mut closed = 0;
+
+        for i in 0..n {
+            let idx = (start + i) % n;
+            match self.shards[idx].try_pop() {
+                Ok(value) => return Ok((value, idx)),
+                Err(PopError::Closed) => closed += 1,
+                Err(PopError::Empty) => {}
+            }
+        }
+
+        if closed == n {
+            Err(PopError::Closed)
+        } else {
+            Err(PopError::Empty)
+        }
+    }
+
+    /// Closes every shard, see [`RingBuffer::close`].
+    pub fn close(&self) -> bool {
+        self.shards
+            .iter()
+            .fold(false, |closed, shard| shard.close() | closed)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shards.iter().all(|shard| shard.is_closed())
+    }
+
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shards.len() * BLOCK_NUM * SLOT_NUM
+    }
+
+    fn local_shard(&self) -> usize {
+        SHARD_ID.with(|id| *id) % self.shards.len()
+    }
+}
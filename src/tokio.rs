@@ -0,0 +1,325 @@
+//! A bounded async channel on top of [`DynRingBuffer`], shaped after `tokio::sync::mpsc`.
+//!
+//! Capacity is tracked with a counter of free permits next to the ring: every queued
+//! message and every outstanding [`Permit`] holds one. The ring gets one block of slack on
+//! top of the capacity, so a push backed by a permit is never refused, even though the
+//! ring itself refuses pushes while part of a block is still waiting to be drained.
+
+use crate::{DynRingBuffer, PopError};
+use ::tokio::sync::Notify;
+
+use core::fmt;
+use core::mem;
+use core::pin::pin;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const BLOCK_NUM: usize = 8;
+
+struct Chan<T> {
+    ring: DynRingBuffer<T>,
+    permits: AtomicUsize,
+    capacity: usize,
+    senders: AtomicUsize,
+    rx_closed: AtomicBool,
+
+    // Senders waiting for a permit and the receiver waiting for a message.
+    tx_notify: Notify,
+    rx_notify: Notify,
+}
+
+/// Creates a channel that holds at most `capacity` messages.
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    if capacity == 0 {
+        panic!("channel capacity must be non-zero")
+    }
+
+    // A push is only refused while less than a whole block is free, so `capacity` items
+    // always fit once `(BLOCK_NUM - 1) * slot_num >= capacity - 1`.
+    let slot_num = (capacity - 1).div_ceil(BLOCK_NUM - 1).max(1);
+
+    let chan = Arc::new(Chan {
+        ring: DynRingBuffer::new(BLOCK_NUM, slot_num),
+        permits: AtomicUsize::new(capacity),
+        capacity,
+        senders: AtomicUsize::new(1),
+        rx_closed: AtomicBool::new(false),
+        tx_notify: Notify::new(),
+        rx_notify: Notify::new(),
+    });
+
+    (
+        Sender {
+            chan: Arc::clone(&chan),
+        },
+        Receiver { chan },
+    )
+}
+
+/// The sending half of a [`channel`].
+pub struct Sender<T> {
+    chan: Arc<Chan<T>>,
+}
+
+/// The receiving half of a [`channel`].
+pub struct Receiver<T> {
+    chan: Arc<Chan<T>>,
+}
+
+/// Room for one message, reserved with [`Sender::reserve`].
+///
+/// Dropping the permit without sending gives the room back.
+pub struct Permit<'a, T> {
+    chan: &'a Chan<T>,
+}
+
+/// Error returned by [`Sender::send`] once the receiver is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// Error returned by [`Sender::try_send`] and [`Sender::try_reserve`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// Every permit is taken.
+    Full(T),
+    /// The receiver is closed.
+    Closed(T),
+}
+
+/// Error returned by [`Receiver::try_recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// No message is queued right now.
+    Empty,
+    /// Every sender is gone, or the receiver was closed, and nothing is left.
+    Disconnected,
+}
+
+impl<T> Sender<T> {
+    /// Waits for room and sends `value`.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        match self.reserve().await {
+            Ok(permit) => {
+                permit.send(value);
+                Ok(())
+            }
+            Err(_) => Err(SendError(value)),
+        }
+    }
+
+    /// Sends `value` if there is room right now.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        match self.try_reserve() {
+            Ok(permit) => {
+                permit.send(value);
+                Ok(())
+            }
+            Err(TrySendError::Full(())) => Err(TrySendError::Full(value)),
+            Err(TrySendError::Closed(())) => Err(TrySendError::Closed(value)),
+        }
+    }
+
+    /// Waits for room for one message and holds on to it.
+    pub async fn reserve(&self) -> Result<Permit<'_, T>, SendError<()>> {
+        let mut notified = pin!(self.chan.tx_notify.notified());
+        loop {
+            // Register before looking, so a permit released in between still wakes us.
+            notified.as_mut().enable();
+            match self.try_reserve() {
+                Ok(permit) => return Ok(permit),
+                Err(TrySendError::Closed(())) => return Err(SendError(())),
+                Err(TrySendError::Full(())) => {}
+            }
+            notified.as_mut().await;
+            notified.set(self.chan.tx_notify.notified());
+        }
+    }
+
+    /// Reserves room for one message if there is any right now.
+    pub fn try_reserve(&self) -> Result<Permit<'_, T>, TrySendError<()>> {
+        let chan = &*self.chan;
+        chan.permits
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .map_err(|_| TrySendError::Full(()))?;
+
+        let permit = Permit { chan };
+        // Checked after taking the permit: either the receiver sees it taken and keeps
+        // waiting for it, or we see the receiver closed and hand it back.
+        if chan.rx_closed.load(Ordering::SeqCst) {
+            return Err(TrySendError::Closed(()));
+        }
+        Ok(permit)
+    }
+
+    /// Returns `true` once the receiver is closed or dropped.
+    pub fn is_closed(&self) -> bool {
+        self.chan.rx_closed.load(Ordering::SeqCst)
+    }
+
+    /// Number of messages that can be sent right now without waiting.
+    pub fn capacity(&self) -> usize {
+        self.chan.permits.load(Ordering::SeqCst)
+    }
+
+    /// The capacity the channel was created with.
+    pub fn max_capacity(&self) -> usize {
+        self.chan.capacity
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.chan.senders.fetch_add(1, Ordering::SeqCst);
+        Self {
+            chan: Arc::clone(&self.chan),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.chan.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // Permits borrow a sender, so nothing can be pushed any more.
+            self.chan.ring.close();
+            self.chan.rx_notify.notify_one();
+        }
+    }
+}
+
+impl<T> Permit<'_, T> {
+    /// Sends `value` into the reserved room.
+    pub fn send(self, value: T) {
+        let chan = self.chan;
+        mem::forget(self);
+
+        let pushed = chan.ring.push(value);
+        assert!(pushed.is_ok(), "a permit always leaves room in the ring");
+        chan.rx_notify.notify_one();
+    }
+}
+
+impl<T> Drop for Permit<'_, T> {
+    fn drop(&mut self) {
+        self.chan.release();
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Waits for the next message, or returns `None` once the channel is closed and empty.
+    pub async fn recv(&mut self) -> Option<T> {
+        let chan = &*self.chan;
+        let mut notified = pin!(chan.rx_notify.notified());
+        loop {
+            notified.as_mut().enable();
+            match chan.try_recv() {
+                Ok(value) => return Some(value),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {}
+            }
+            notified.as_mut().await;
+            notified.set(chan.rx_notify.notified());
+        }
+    }
+
+    /// Waits for at least one message and moves up to `limit` into `buffer`.
+    ///
+    /// Returns how many were received, which is zero only if `limit` is zero or the
+    /// channel is closed and empty.
+    pub async fn recv_many(&mut self, buffer: &mut Vec<T>, limit: usize) -> usize {
+        if limit == 0 {
+            return 0;
+        }
+        let Some(first) = self.recv().await else {
+            return 0;
+        };
+        buffer.push(first);
+
+        let mut received = 1;
+        while received < limit {
+            match self.chan.try_recv() {
+                Ok(value) => buffer.push(value),
+                Err(_) => break,
+            }
+            received += 1;
+        }
+        received
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.chan.try_recv()
+    }
+
+    /// Stops senders from sending more, while the queued messages and those sent through
+    /// permits already handed out can still be received.
+    pub fn close(&mut self) {
+        self.chan.rx_closed.store(true, Ordering::SeqCst);
+        self.chan.tx_notify.notify_waiters();
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+        while self.chan.try_recv().is_ok() {}
+    }
+}
+
+impl<T> Chan<T> {
+    fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self.ring.try_pop() {
+            Ok(value) => {
+                self.release();
+                Ok(value)
+            }
+            Err(PopError::Closed) => Err(TryRecvError::Disconnected),
+            // A closed receiver is done once every permit is back.
+            Err(PopError::Empty)
+                if self.rx_closed.load(Ordering::SeqCst)
+                    && self.permits.load(Ordering::SeqCst) == self.capacity =>
+            {
+                Err(TryRecvError::Disconnected)
+            }
+            Err(PopError::Empty) => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn release(&self) {
+        self.permits.fetch_add(1, Ordering::SeqCst);
+        self.tx_notify.notify_one();
+        if self.rx_closed.load(Ordering::SeqCst) {
+            self.rx_notify.notify_one();
+        }
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("send on a closed channel")
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for SendError<T> {}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => f.write_str("send on a full channel"),
+            TrySendError::Closed(_) => f.write_str("send on a closed channel"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for TrySendError<T> {}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => f.write_str("receive on an empty channel"),
+            TryRecvError::Disconnected => f.write_str("receive on a closed channel"),
+        }
+    }
+}
+
+impl std::error::Error for TryRecvError {}
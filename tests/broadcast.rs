@@ -0,0 +1,198 @@
+use bbring::{BroadcastPolicy, BroadcastRing, PushError, RecvError};
+use crossbeam_utils::thread::scope;
+
+use std::sync::Arc;
+
+#[test]
+fn smoke() {
+    let q = BroadcastRing::<i32, 4, 2>::new(BroadcastPolicy::Blocking, 2);
+    let mut a = q.subscribe().unwrap();
+    q.push(1).unwrap();
+    let mut b = q.subscribe().unwrap();
+    assert!(q.subscribe().is_none());
+    q.push(2).unwrap();
+
+    assert_eq!(a.recv(), Ok(1));
+    assert_eq!(a.recv(), Ok(2));
+    assert_eq!(a.recv(), Err(RecvError::Empty));
+    // `b` only sees what was pushed after it subscribed.
+    assert_eq!(b.recv(), Ok(2));
+    assert_eq!(b.recv(), Err(RecvError::Empty));
+
+    drop(b);
+    assert!(q.subscribe().is_some());
+}
+
+#[test]
+fn blocking() {
+    let q = BroadcastRing::<i32, 4, 2>::new(BroadcastPolicy::Blocking, 2);
+    let mut fast = q.subscribe().unwrap();
+    let mut slow = q.subscribe().unwrap();
+
+    for i in 0..8 {
+        q.push(i).unwrap();
+        assert_eq!(fast.recv(), Ok(i));
+    }
+    // The slow subscriber still holds the oldest block.
+    assert_eq!(q.push(8), Err(PushError::Full(8)));
+    assert_eq!(slow.recv(), Ok(0));
+    assert_eq!(slow.recv(), Ok(1));
+    assert_eq!(q.push(8), Err(PushError::Full(8)));
+    assert_eq!(slow.recv(), Ok(2));
+    q.push(8).unwrap();
+
+    for i in 3..9 {
+        assert_eq!(slow.recv(), Ok(i));
+    }
+    assert_eq!(fast.recv(), Ok(8));
+}
+
+#[test]
+fn lagging() {
+    let q = BroadcastRing::<i32, 4, 2>::new(BroadcastPolicy::Lagging, 1);
+    let mut sub = q.subscribe().unwrap();
+    assert_eq!(sub.recv(), Err(RecvError::Empty));
+    assert_eq!(q.policy(), BroadcastPolicy::Lagging);
+
+    for i in 0..20 {
+        q.push(i).unwrap();
+    }
+    // Only the last blocks are still there, the rest was recycled.
+    assert_eq!(sub.recv(), Err(RecvError::Lagged(12)));
+    for i in 12..20 {
+        assert_eq!(sub.recv(), Ok(i));
+    }
+    assert_eq!(sub.recv(), Err(RecvError::Empty));
+}
+
+#[test]
+fn lagging_partial_block() {
+    let q = BroadcastRing::<i32, 4, 3>::new(BroadcastPolicy::Lagging, 2);
+    q.push(0).unwrap();
+    // Joins with the head block partly filled, and reads into the next one.
+    let mut sub = q.subscribe().unwrap();
+    for i in 1..6 {
+        q.push(i).unwrap();
+    }
+    let mut early = q.subscribe().unwrap();
+    for i in 1..5 {
+        assert_eq!(sub.recv(), Ok(i));
+    }
+
+    for i in 6..30 {
+        q.push(i).unwrap();
+    }
+    // Blocks of 3 from 0, the oldest one left starts at 18.
+    assert_eq!(sub.recv(), Err(RecvError::Lagged(13)));
+    assert_eq!(early.recv(), Err(RecvError::Lagged(12)));
+    for i in 18..30 {
+        assert_eq!(sub.recv(), Ok(i));
+        assert_eq!(early.recv(), Ok(i));
+    }
+    assert_eq!(sub.recv(), Err(RecvError::Empty));
+}
+
+#[test]
+fn drop_values() {
+    let item = Arc::new(());
+    {
+        let q = BroadcastRing::<Arc<()>, 4, 2>::new(BroadcastPolicy::Lagging, 1);
+        let mut sub = q.subscribe().unwrap();
+        for _ in 0..20 {
+            q.push(item.clone()).unwrap();
+        }
+        assert_eq!(Arc::strong_count(&item), 1 + 8);
+        assert!(matches!(sub.recv(), Err(RecvError::Lagged(_))));
+        let got = sub.recv().unwrap();
+        assert_eq!(Arc::strong_count(&item), 1 + 8 + 1);
+        drop(got);
+    }
+    assert_eq!(Arc::strong_count(&item), 1);
+}
+
+#[test]
+fn fan_out() {
+    #[cfg(miri)]
+    const COUNT: usize = 50;
+    #[cfg(not(miri))]
+    const COUNT: usize = 1_000;
+    const PTHREADS: usize = 2;
+    const CTHREADS: usize = 3;
+
+    let q = BroadcastRing::<usize, 4, 2>::new(BroadcastPolicy::Blocking, CTHREADS);
+    let subs = (0..CTHREADS)
+        .map(|_| q.subscribe().unwrap())
+        .collect::<Vec<_>>();
+
+    scope(|scope| {
+        for mut sub in subs {
+            scope.spawn(move |_| {
+                let mut seen = vec![0; COUNT];
+                let mut last = [None; PTHREADS];
+                for _ in 0..COUNT * PTHREADS {
+                    let n = loop {
+                        match sub.recv() {
+                            Ok(x) => break x,
+                            Err(RecvError::Empty) => {}
+                            Err(RecvError::Lagged(_)) => panic!("blocking ring lagged"),
+                        }
+                    };
+                    let (t, i) = (n / COUNT, n % COUNT);
+                    assert!(last[t] < Some(i));
+                    last[t] = Some(i);
+                    seen[i] += 1;
+                }
+                assert!(seen.iter().all(|&c| c == PTHREADS));
+            });
+        }
+        for t in 0..PTHREADS {
+            let q = &q;
+            scope.spawn(move |_| {
+                for i in 0..COUNT {
+                    while q.push(t * COUNT + i).is_err() {}
+                }
+            });
+        }
+    })
+    .unwrap();
+}
+
+#[test]
+fn lagging_fan_out() {
+    #[cfg(miri)]
+    const COUNT: usize = 50;
+    #[cfg(not(miri))]
+    const COUNT: usize = 10_000;
+    const CTHREADS: usize = 3;
+
+    let q = BroadcastRing::<String, 4, 2>::new(BroadcastPolicy::Lagging, CTHREADS);
+    let subs = (0..CTHREADS)
+        .map(|_| q.subscribe().unwrap())
+        .collect::<Vec<_>>();
+
+    scope(|scope| {
+        for mut sub in subs {
+            scope.spawn(move |_| {
+                // Whatever is not received must be reported as missed, in order.
+                let mut next = 0;
+                while next < COUNT {
+                    match sub.recv() {
+                        Ok(x) => {
+                            assert_eq!(x, next.to_string());
+                            next += 1;
+                        }
+                        Err(RecvError::Lagged(n)) => next += n,
+                        Err(RecvError::Empty) => {}
+                    }
+                }
+                assert_eq!(next, COUNT);
+            });
+        }
+        scope.spawn(|_| {
+            for i in 0..COUNT {
+                q.push(i.to_string()).unwrap();
+            }
+        });
+    })
+    .unwrap();
+}
@@ -0,0 +1,180 @@
+use bbring::{PushError, Steal, Worker};
+use crossbeam_utils::thread::scope;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+fn steal<T, const B: usize, const S: usize>(stealer: &bbring::Stealer<T, B, S>) -> Option<Vec<T>> {
+    loop {
+        match stealer.steal_batch() {
+            Steal::Success(batch) => return Some(batch.collect()),
+            Steal::Empty => return None,
+            Steal::Retry => {}
+        }
+    }
+}
+
+#[test]
+fn smoke() {
+    let w = Worker::<i32, 4, 2>::new();
+    let s = w.stealer();
+
+    for i in 0..5 {
+        w.push(i).unwrap();
+    }
+    // Oldest whole block to the thief, newest item to the owner.
+    assert_eq!(steal(&s), Some(vec![0, 1]));
+    assert_eq!(w.pop(), Some(4));
+    assert_eq!(w.pop(), Some(3));
+    assert_eq!(w.pop(), Some(2));
+    assert_eq!(w.pop(), None);
+    assert_eq!(steal(&s), None);
+}
+
+#[test]
+fn partial_block_stays_home() {
+    let w = Worker::<i32, 4, 4>::new();
+    let s = w.stealer();
+
+    for i in 0..3 {
+        w.push(i).unwrap();
+    }
+    assert!(s.is_empty());
+    assert_eq!(steal(&s), None);
+
+    w.push(3).unwrap();
+    assert!(!s.is_empty());
+    assert_eq!(steal(&s), Some(vec![0, 1, 2, 3]));
+}
+
+#[test]
+fn owner_takes_back_last_block() {
+    let w = Worker::<i32, 4, 2>::new();
+    let s = w.stealer();
+
+    for i in 0..6 {
+        w.push(i).unwrap();
+    }
+    for i in (0..6).rev() {
+        assert_eq!(w.pop(), Some(i));
+    }
+    assert_eq!(w.pop(), None);
+    assert_eq!(steal(&s), None);
+
+    // The deque still works after moving back past every block.
+    for lap in 0..3 {
+        for i in 0..8 {
+            w.push(lap * 8 + i).unwrap();
+        }
+        for i in (0..8).rev() {
+            assert_eq!(w.pop(), Some(lap * 8 + i));
+        }
+    }
+}
+
+#[test]
+fn full_while_block_is_stolen() {
+    let w = Worker::<i32, 2, 2>::new();
+    let s = w.stealer();
+
+    for i in 0..4 {
+        w.push(i).unwrap();
+    }
+    assert_eq!(w.push(4), Err(PushError::Full(4)));
+
+    // The stolen block can only be refilled once the thief is done with it.
+    let Steal::Success(mut batch) = s.steal_batch() else {
+        panic!("nothing to steal")
+    };
+    assert_eq!(batch.len(), 2);
+    assert_eq!(batch.next(), Some(0));
+    assert_eq!(w.push(4), Err(PushError::Full(4)));
+    drop(batch);
+
+    w.push(4).unwrap();
+    assert_eq!(steal(&s), Some(vec![2, 3]));
+    assert_eq!(w.pop(), Some(4));
+    assert_eq!(w.pop(), None);
+}
+
+#[test]
+fn drops() {
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let w = Worker::<Counted, 4, 2>::new();
+    let s = w.stealer();
+    for _ in 0..7 {
+        w.push(Counted(dropped.clone())).ok().unwrap();
+    }
+
+    let Steal::Success(mut batch) = s.steal_batch() else {
+        panic!("nothing to steal")
+    };
+    drop(batch.next());
+    drop(batch);
+    assert_eq!(dropped.load(Ordering::SeqCst), 2);
+
+    // The owner's items go with the worker, the published ones with the last handle.
+    drop(w);
+    assert_eq!(dropped.load(Ordering::SeqCst), 3);
+    drop(s);
+    assert_eq!(dropped.load(Ordering::SeqCst), 7);
+}
+
+#[test]
+fn stress() {
+    #[cfg(miri)]
+    const COUNT: usize = 200;
+    #[cfg(not(miri))]
+    const COUNT: usize = 100_000;
+    const THIEVES: usize = 3;
+
+    let w = Worker::<usize, 8, 4>::new();
+    let seen = (0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+    let done = AtomicBool::new(false);
+
+    scope(|scope| {
+        for _ in 0..THIEVES {
+            let s = w.stealer();
+            let (seen, done) = (&seen, &done);
+            scope.spawn(move |_| {
+                while !done.load(Ordering::SeqCst) {
+                    if let Steal::Success(batch) = s.steal_batch() {
+                        for n in batch {
+                            seen[n].fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
+                }
+            });
+        }
+
+        for i in 0..COUNT {
+            while w.push(i).is_err() {
+                if let Some(n) = w.pop() {
+                    seen[n].fetch_add(1, Ordering::SeqCst);
+                }
+            }
+            if i % 3 == 0
+                && let Some(n) = w.pop()
+            {
+                seen[n].fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        while let Some(n) = w.pop() {
+            seen[n].fetch_add(1, Ordering::SeqCst);
+        }
+        done.store(true, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    for c in &seen {
+        assert_eq!(c.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,120 @@
+#![cfg(all(feature = "eventfd", target_os = "linux"))]
+
+use bbring::PopError;
+use bbring::eventfd::EventRing;
+use crossbeam_utils::thread::scope;
+use mio::{Events, Poll, Token};
+
+use std::os::fd::AsRawFd;
+use std::thread;
+use std::time::Duration;
+
+// Reads the eventfd counter without blocking, zero if it is not readable.
+fn take_count<T, const B: usize, const S: usize>(ring: &EventRing<T, B, S>) -> u64 {
+    let mut count = 0u64;
+    unsafe { libc::read(ring.as_raw_fd(), (&raw mut count).cast(), 8) };
+    count
+}
+
+#[test]
+fn signals_only_when_armed() {
+    let ring = EventRing::<i32, 4, 2>::new().unwrap();
+
+    // Nobody has waited yet, so pushes stay quiet.
+    ring.push(1).unwrap();
+    ring.push(2).unwrap();
+    assert_eq!(take_count(&ring), 0);
+
+    assert_eq!(ring.try_pop(), Ok(1));
+    assert_eq!(ring.try_pop(), Ok(2));
+    assert_eq!(ring.try_pop(), Err(PopError::Empty));
+
+    // The first push after finding it empty writes the fd, the rest do not.
+    ring.push(3).unwrap();
+    ring.push(4).unwrap();
+    assert_eq!(take_count(&ring), 1);
+
+    assert_eq!(ring.try_pop(), Ok(3));
+    assert_eq!(ring.try_pop(), Ok(4));
+    assert_eq!(ring.try_pop(), Err(PopError::Empty));
+    assert!(ring.close());
+    assert_eq!(take_count(&ring), 1);
+    assert_eq!(ring.try_pop(), Err(PopError::Closed));
+}
+
+#[test]
+fn epoll_level_triggered() {
+    let ring = EventRing::<i32, 4, 2>::new().unwrap();
+    let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+    assert!(epfd >= 0);
+
+    let mut event = libc::epoll_event {
+        events: libc::EPOLLIN as u32,
+        u64: 7,
+    };
+    let res = unsafe { libc::epoll_ctl(epfd, libc::EPOLL_CTL_ADD, ring.as_raw_fd(), &mut event) };
+    assert_eq!(res, 0);
+
+    let wait = |timeout| {
+        let mut events = [libc::epoll_event { events: 0, u64: 0 }; 4];
+        let n = unsafe { libc::epoll_wait(epfd, events.as_mut_ptr(), 4, timeout) };
+        assert!(n >= 0);
+        events[..n as usize]
+            .iter()
+            .map(|e| e.u64)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(ring.try_pop(), Err(PopError::Empty));
+    assert_eq!(wait(0), []);
+    ring.push(1).unwrap();
+    assert_eq!(wait(0), [7]);
+    assert_eq!(ring.try_pop(), Ok(1));
+
+    // Finding it empty again resets the counter, so the level drops.
+    assert_eq!(ring.try_pop(), Err(PopError::Empty));
+    assert_eq!(wait(0), []);
+
+    unsafe { libc::close(epfd) };
+}
+
+#[test]
+fn mio_loop() {
+    const COUNT: usize = 10_000;
+
+    let ring = EventRing::<usize, 4, 16>::new().unwrap();
+    let mut poll = Poll::new().unwrap();
+    ring.register(poll.registry(), Token(0)).unwrap();
+
+    scope(|scope| {
+        scope.spawn(|_| {
+            for i in 0..COUNT {
+                while ring.push(i).is_err() {
+                    thread::yield_now();
+                }
+                // Let the consumer run dry now and then, so it has to wait on the fd.
+                if i % 1000 == 0 {
+                    thread::sleep(Duration::from_millis(1));
+                }
+            }
+            ring.close();
+        });
+
+        let mut events = Events::with_capacity(4);
+        let mut next = 0;
+        loop {
+            match ring.try_pop() {
+                Ok(n) => {
+                    assert_eq!(n, next);
+                    next += 1;
+                }
+                Err(PopError::Empty) => poll.poll(&mut events, None).unwrap(),
+                Err(PopError::Closed) => break,
+            }
+        }
+        assert_eq!(next, COUNT);
+    })
+    .unwrap();
+
+    ring.deregister(poll.registry()).unwrap();
+}
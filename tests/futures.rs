@@ -0,0 +1,152 @@
+#![cfg(feature = "futures")]
+
+use bbring::PushError;
+use bbring::futures::channel;
+use futures::executor::block_on;
+use futures::task::{ArcWake, noop_waker_ref, waker};
+use futures::{FutureExt, SinkExt, StreamExt, stream};
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::thread;
+
+struct Flag(AtomicBool);
+
+impl ArcWake for Flag {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn smoke() {
+    let (mut tx, mut rx) = channel::<i32, 4, 2>();
+    block_on(async {
+        tx.send(7).await.unwrap();
+        assert_eq!(rx.next().await, Some(7));
+        tx.send(8).await.unwrap();
+        assert_eq!(rx.next().await, Some(8));
+        assert!(rx.next().now_or_never().is_none());
+
+        tx.close().await.unwrap();
+        assert_eq!(rx.next().await, None);
+        assert!(matches!(tx.send(9).await, Err(PushError::Closed(9))));
+    });
+}
+
+#[test]
+fn backpressure() {
+    let (mut tx, mut rx) = channel::<i32, 2, 2>();
+    block_on(async {
+        for i in 0..4 {
+            tx.send(i).await.unwrap();
+        }
+    });
+
+    // The fifth item is held back and the sink stops accepting more.
+    let flag = Arc::new(Flag(AtomicBool::new(false)));
+    let flag_waker = waker(Arc::clone(&flag));
+    let mut cx = Context::from_waker(&flag_waker);
+    tx.start_send_unpin(4).unwrap();
+    assert!(tx.poll_ready_unpin(&mut cx).is_pending());
+
+    // One pop leaves the next block half full, which is still no room.
+    let mut noop = Context::from_waker(noop_waker_ref());
+    assert_eq!(rx.poll_next_unpin(&mut noop), Poll::Ready(Some(0)));
+    assert!(flag.0.swap(false, Ordering::SeqCst));
+    assert!(tx.poll_ready_unpin(&mut cx).is_pending());
+
+    assert_eq!(rx.poll_next_unpin(&mut noop), Poll::Ready(Some(1)));
+    assert!(flag.0.load(Ordering::SeqCst));
+    assert!(tx.poll_ready_unpin(&mut cx).is_ready());
+
+    drop(tx);
+    let rest = block_on(rx.collect::<Vec<_>>());
+    assert_eq!(rest, [2, 3, 4]);
+}
+
+#[test]
+fn receivers_dropped() {
+    let (mut tx, rx) = channel::<i32, 2, 2>();
+    let rx2 = rx.clone();
+    block_on(async {
+        for i in 0..4 {
+            tx.send(i).await.unwrap();
+        }
+    });
+
+    // A sender parked on the full ring is woken and refused once nobody can pop.
+    let flag = Arc::new(Flag(AtomicBool::new(false)));
+    let flag_waker = waker(Arc::clone(&flag));
+    let mut cx = Context::from_waker(&flag_waker);
+    tx.start_send_unpin(4).unwrap();
+    assert!(tx.poll_ready_unpin(&mut cx).is_pending());
+
+    drop(rx);
+    assert!(!flag.0.load(Ordering::SeqCst));
+    assert!(tx.poll_ready_unpin(&mut cx).is_pending());
+    drop(rx2);
+    assert!(flag.0.load(Ordering::SeqCst));
+    assert!(matches!(
+        tx.poll_ready_unpin(&mut cx),
+        Poll::Ready(Err(PushError::Closed(4)))
+    ));
+    assert!(matches!(block_on(tx.send(5)), Err(PushError::Closed(5))));
+}
+
+#[test]
+fn forward() {
+    const COUNT: usize = 10_000;
+
+    let (tx, rx) = channel::<usize, 4, 2>();
+    let (sent, received) = block_on(async {
+        futures::join!(
+            stream::iter(0..COUNT).map(Ok).forward(tx),
+            rx.collect::<Vec<_>>()
+        )
+    });
+    sent.unwrap();
+    assert_eq!(received, (0..COUNT).collect::<Vec<_>>());
+}
+
+#[test]
+fn mpmc() {
+    const COUNT: usize = 2_000;
+    const THREADS: usize = 4;
+
+    let (tx, rx) = channel::<usize, 4, 2>();
+    let v = Arc::new((0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>());
+
+    let consumers = (0..THREADS)
+        .map(|_| {
+            let (rx, v) = (rx.clone(), Arc::clone(&v));
+            thread::spawn(move || {
+                block_on(rx.for_each(|n| {
+                    v[n].fetch_add(1, Ordering::SeqCst);
+                    async {}
+                }))
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(rx);
+
+    let producers = (0..THREADS)
+        .map(|_| {
+            let mut tx = tx.clone();
+            thread::spawn(move || block_on(tx.send_all(&mut stream::iter(0..COUNT).map(Ok))))
+        })
+        .collect::<Vec<_>>();
+    drop(tx);
+
+    for p in producers {
+        p.join().unwrap().unwrap();
+    }
+    for c in consumers {
+        c.join().unwrap();
+    }
+
+    for c in v.iter() {
+        assert_eq!(c.load(Ordering::SeqCst), THREADS);
+    }
+}
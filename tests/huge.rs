@@ -0,0 +1,90 @@
+#![cfg(all(feature = "huge-pages", target_os = "linux"))]
+
+use bbring::huge::Backing;
+use bbring::{RingBuffer, SlotLayout};
+
+use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Big enough for a huge page of 2 MiB, and not much more so the test stays cheap.
+type Large = RingBuffer<usize, 64, 8192>;
+
+fn fill_and_drain(q: &Large) {
+    for lap in 0..2 {
+        for i in 0..q.capacity() {
+            q.push(lap + i).unwrap();
+        }
+        for i in 0..q.capacity() {
+            assert_eq!(q.pop(), Some(lap + i));
+        }
+    }
+}
+
+#[test]
+fn small_ring_stays_on_heap() {
+    let q = RingBuffer::<u32, 4, 8>::new_huge();
+    assert_eq!(q.backing(), Backing::Heap);
+    q.push(1).unwrap();
+    assert_eq!(q.pop(), Some(1));
+}
+
+#[test]
+fn fallback() {
+    let q = Large::new_huge();
+    fill_and_drain(&q);
+
+    // Without reserved huge pages the explicit mapping fails, and with transparent huge
+    // pages switched off as well, the advised one is not tried.
+    let free = fs::read_to_string("/proc/meminfo")
+        .unwrap()
+        .lines()
+        .find_map(|l| l.strip_prefix("HugePages_Free:"))
+        .map_or(0, |n| n.trim().parse::<usize>().unwrap());
+    let thp = fs::read_to_string("/sys/kernel/mm/transparent_hugepage/enabled")
+        .unwrap_or_else(|_| "[never]".into());
+
+    if free == 0 {
+        assert_ne!(q.backing(), Backing::HugeTlb);
+        if thp.contains("[never]") {
+            assert_eq!(q.backing(), Backing::Heap);
+        }
+    }
+}
+
+#[test]
+fn interleaved() {
+    let q = Large::new_huge_with_layout(SlotLayout::Interleaved);
+    fill_and_drain(&q);
+}
+
+#[cfg(feature = "step-hook")]
+#[test]
+fn failed_mappings() {
+    bbring::hook::fail_huge_mappings(true);
+    let q = Large::new_huge();
+    bbring::hook::fail_huge_mappings(false);
+
+    assert_eq!(q.backing(), Backing::Heap);
+    fill_and_drain(&q);
+}
+
+#[test]
+fn drops_items() {
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let q = RingBuffer::<Counted, 64, 8192>::new_huge();
+    for _ in 0..1000 {
+        q.push(Counted(dropped.clone())).ok().unwrap();
+    }
+    drop(q.pop());
+    drop(q);
+    assert_eq!(dropped.load(Ordering::SeqCst), 1000);
+}
@@ -0,0 +1,251 @@
+// Records concurrent histories of push/pop with invocation and response timestamps and
+// checks that each one can be explained by a sequential bounded FIFO queue.
+use bbring::RingBuffer;
+use crossbeam_utils::thread::scope;
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Barrier;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    // Pushed value and whether the push succeeded.
+    Push(usize, bool),
+    Pop(Option<usize>),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Event {
+    inv: usize,
+    res: usize,
+    op: Op,
+}
+
+// The sequential specification.
+//
+// Retry-new only reuses a block once every item of its previous lap has been popped, so
+// a push may be refused while up to `slot_num - 1` slots are still free. Refusing a push
+// is therefore legal as soon as less than a whole block is free, and a successful push
+// needs a free slot. A pop returns the oldest item and may only come back empty from an
+// empty queue.
+struct Spec {
+    capacity: usize,
+    slot_num: usize,
+}
+
+impl Spec {
+    fn apply(&self, queue: &mut VecDeque<usize>, op: Op) -> bool {
+        match op {
+            Op::Push(v, true) if queue.len() < self.capacity => {
+                queue.push_back(v);
+                true
+            }
+            Op::Push(_, false) => queue.len() + self.slot_num > self.capacity,
+            Op::Pop(Some(v)) if queue.front() == Some(&v) => {
+                queue.pop_front();
+                true
+            }
+            Op::Pop(None) => queue.is_empty(),
+            _ => false,
+        }
+    }
+
+    // Depth-first search over the orders that respect real time, remembering which
+    // (linearized set, queue contents) states were already explored.
+    fn linearizable(&self, history: &[Event]) -> bool {
+        assert!(history.len() <= 64);
+        let mut seen = HashSet::new();
+        self.search(history, 0, VecDeque::new(), &mut seen)
+    }
+
+    fn search(
+        &self,
+        history: &[Event],
+        done: u64,
+        queue: VecDeque<usize>,
+        seen: &mut HashSet<(u64, VecDeque<usize>)>,
+    ) -> bool {
+        let pending = (0..history.len()).filter(|&i| done & (1 << i) == 0);
+        let Some(first_res) = pending.clone().map(|i| history[i].res).min() else {
+            return true;
+        };
+
+        // An operation can go next only if no pending one has already returned before it
+        // was invoked.
+        for i in pending.filter(|&i| history[i].inv < first_res) {
+            let mut next = queue.clone();
+            if !self.apply(&mut next, history[i].op) {
+                continue;
+            }
+            let done = done | (1 << i);
+            if seen.insert((done, next.clone())) && self.search(history, done, next, seen) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// Tiny deterministic generator so every history can be replayed from its seed.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+fn record<const BLOCK_NUM: usize, const SLOT_NUM: usize>(
+    seed: u64,
+    threads: usize,
+    ops: usize,
+) -> Vec<Event> {
+    let q = RingBuffer::<usize, BLOCK_NUM, SLOT_NUM>::new();
+    let clock = AtomicUsize::new(0);
+    let mut rng = XorShift(seed);
+
+    let run = |op: Op| {
+        let inv = clock.fetch_add(1, Ordering::SeqCst);
+        let op = match op {
+            Op::Push(v, _) => Op::Push(v, q.push(v).is_ok()),
+            Op::Pop(_) => Op::Pop(q.pop()),
+        };
+        let res = clock.fetch_add(1, Ordering::SeqCst);
+        Event { inv, res, op }
+    };
+
+    // Start from a random fill level, so that full queues show up as often as empty ones.
+    let prefill = rng.next() as usize % (BLOCK_NUM * SLOT_NUM + 1);
+    let mut history = (0..prefill)
+        .map(|i| run(Op::Push(1000 + i, true)))
+        .collect::<Vec<_>>();
+
+    let plans = (0..threads)
+        .map(|t| {
+            (0..ops)
+                .map(|i| {
+                    if rng.next().is_multiple_of(2) {
+                        Op::Push(t * 100 + i, true)
+                    } else {
+                        Op::Pop(None)
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let barrier = Barrier::new(threads);
+    scope(|scope| {
+        let handles = plans
+            .iter()
+            .map(|plan| {
+                let (run, barrier) = (&run, &barrier);
+                scope.spawn(move |_| {
+                    barrier.wait();
+                    plan.iter().map(|&op| run(op)).collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        for h in handles {
+            history.extend(h.join().unwrap());
+        }
+    })
+    .unwrap();
+
+    history
+}
+
+fn check<const BLOCK_NUM: usize, const SLOT_NUM: usize>(histories: u64) {
+    let spec = Spec {
+        capacity: BLOCK_NUM * SLOT_NUM,
+        slot_num: SLOT_NUM,
+    };
+
+    for seed in 1..=histories {
+        let history = record::<BLOCK_NUM, SLOT_NUM>(seed, 3, 4);
+        assert!(
+            spec.linearizable(&history),
+            "{BLOCK_NUM}x{SLOT_NUM} seed {seed}: {history:#?}"
+        );
+    }
+}
+
+#[cfg(miri)]
+const HISTORIES: u64 = 2;
+#[cfg(not(miri))]
+const HISTORIES: u64 = 500;
+
+#[test]
+fn spec_rejects_reordering() {
+    let spec = Spec {
+        capacity: 2,
+        slot_num: 1,
+    };
+    let ev = |inv, res, op| Event { inv, res, op };
+
+    let fifo = [
+        ev(0, 1, Op::Push(1, true)),
+        ev(2, 3, Op::Push(2, true)),
+        ev(4, 5, Op::Pop(Some(1))),
+    ];
+    assert!(spec.linearizable(&fifo));
+
+    let lifo = [
+        ev(0, 1, Op::Push(1, true)),
+        ev(2, 3, Op::Push(2, true)),
+        ev(4, 5, Op::Pop(Some(2))),
+    ];
+    assert!(!spec.linearizable(&lifo));
+
+    // Overlapping pushes may take effect in either order.
+    let overlap = [
+        ev(0, 3, Op::Push(1, true)),
+        ev(1, 2, Op::Push(2, true)),
+        ev(4, 5, Op::Pop(Some(2))),
+    ];
+    assert!(spec.linearizable(&overlap));
+
+    let empty_pop = [ev(0, 1, Op::Push(1, true)), ev(2, 3, Op::Pop(None))];
+    assert!(!spec.linearizable(&empty_pop));
+
+    let early_full = [ev(0, 1, Op::Push(1, true)), ev(2, 3, Op::Push(2, false))];
+    assert!(!spec.linearizable(&early_full));
+}
+
+#[test]
+fn linearizable_1x1() {
+    check::<1, 1>(HISTORIES);
+}
+
+#[test]
+fn linearizable_1x4() {
+    check::<1, 4>(HISTORIES);
+}
+
+#[test]
+fn linearizable_2x1() {
+    check::<2, 1>(HISTORIES);
+}
+
+#[test]
+fn linearizable_2x2() {
+    check::<2, 2>(HISTORIES);
+}
+
+#[test]
+fn linearizable_4x2() {
+    check::<4, 2>(HISTORIES);
+}
+
+#[test]
+fn linearizable_2x4() {
+    check::<2, 4>(HISTORIES);
+}
+
+#[test]
+fn linearizable_8x1() {
+    check::<8, 1>(HISTORIES);
+}
@@ -0,0 +1,256 @@
+// Small, fully deterministic cases for the unsafe slot handling. Run them with
+//
+//     MIRIFLAGS="-Zmiri-strict-provenance -Zmiri-many-seeds" cargo +nightly miri test --test miri
+use bbring::{BroadcastPolicy, BroadcastRing, RingBuffer, Steal, Worker};
+use crossbeam_utils::thread::scope;
+use std::sync::Arc;
+
+use std::cell::Cell;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Counts drops into a shared counter.
+#[derive(Debug)]
+struct DropCount<'a>(&'a AtomicUsize, usize);
+
+impl Drop for DropCount<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Zst;
+
+thread_local! {
+    static ZST_DROPS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Debug)]
+struct DropZst;
+
+impl Drop for DropZst {
+    fn drop(&mut self) {
+        ZST_DROPS.with(|d| d.set(d.get() + 1));
+    }
+}
+
+// Panics when dropped if the flag is set.
+#[derive(Debug)]
+struct PanicOnDrop<'a>(&'a AtomicUsize, bool);
+
+impl Drop for PanicOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        if self.1 {
+            panic!("boom");
+        }
+    }
+}
+
+#[test]
+fn drop_values() {
+    let drops = AtomicUsize::new(0);
+    {
+        let q = RingBuffer::<DropCount, 4, 2>::new();
+        // Go around a lap so the leftovers straddle the wrap.
+        for i in 0..8 {
+            q.push(DropCount(&drops, i)).unwrap();
+        }
+        for i in 0..5 {
+            assert_eq!(q.pop().unwrap().1, i);
+        }
+        for i in 8..12 {
+            q.push(DropCount(&drops, i)).unwrap();
+        }
+        let rejected = q.push(DropCount(&drops, 12)).unwrap_err().into_inner();
+        assert_eq!(rejected.1, 12);
+        assert_eq!(drops.load(Ordering::SeqCst), 5);
+    }
+    // The rejected value and the seven left in the queue.
+    assert_eq!(drops.load(Ordering::SeqCst), 13);
+}
+
+#[test]
+fn zst() {
+    let q = RingBuffer::<Zst, 4, 2>::new();
+    for _ in 0..8 {
+        q.push(Zst).unwrap();
+    }
+    assert!(q.push(Zst).is_err());
+    for _ in 0..8 {
+        assert_eq!(q.pop(), Some(Zst));
+    }
+    assert!(q.pop().is_none());
+
+    ZST_DROPS.with(|d| d.set(0));
+    {
+        let q = RingBuffer::<DropZst, 2, 2>::new();
+        for _ in 0..3 {
+            q.push(DropZst).unwrap();
+        }
+        drop(q.pop());
+        assert_eq!(ZST_DROPS.with(Cell::get), 1);
+    }
+    assert_eq!(ZST_DROPS.with(Cell::get), 3);
+}
+
+#[test]
+fn zst_laps() {
+    ZST_DROPS.with(|d| d.set(0));
+    {
+        let q = RingBuffer::<DropZst, 2, 2>::new_boxed();
+        for lap in 0..5 {
+            for _ in 0..4 {
+                q.push(DropZst).unwrap();
+            }
+            assert!(q.push(DropZst).is_err());
+            for _ in 0..4 {
+                drop(q.pop().unwrap());
+            }
+            assert!(q.pop().is_none());
+            // Every lap drops the four popped values and the rejected one.
+            assert_eq!(ZST_DROPS.with(Cell::get), (lap + 1) * 5);
+        }
+        q.push(DropZst).unwrap();
+    }
+    assert_eq!(ZST_DROPS.with(Cell::get), 26);
+}
+
+#[test]
+fn new_boxed_drop_values() {
+    let drops = AtomicUsize::new(0);
+    {
+        let q = RingBuffer::<DropCount, 2, 2>::new_boxed();
+        for i in 0..3 {
+            q.push(DropCount(&drops, i)).unwrap();
+        }
+        assert_eq!(q.pop().unwrap().1, 0);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+    assert_eq!(drops.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn panicking_drop_during_drain() {
+    let drops = AtomicUsize::new(0);
+    let q = RingBuffer::<PanicOnDrop, 4, 2>::new();
+    for i in 0..6 {
+        q.push(PanicOnDrop(&drops, i == 2)).unwrap();
+    }
+
+    let res = catch_unwind(AssertUnwindSafe(|| drop(q)));
+    assert!(res.is_err());
+    // Everything after the panicking item is still dropped.
+    assert_eq!(drops.load(Ordering::SeqCst), 6);
+}
+
+#[test]
+fn snapshot_clones() {
+    let q = RingBuffer::<String, 2, 2>::new();
+    q.push("a".to_string()).unwrap();
+    q.push("b".to_string()).unwrap();
+    q.push("c".to_string()).unwrap();
+    assert_eq!(q.pop().as_deref(), Some("a"));
+    assert_eq!(q.snapshot(), ["b", "c"]);
+}
+
+#[test]
+fn broadcast_recycle() {
+    let drops = AtomicUsize::new(0);
+    {
+        let q = BroadcastRing::<Arc<DropCount>, 2, 2>::new(BroadcastPolicy::Lagging, 1);
+        let mut sub = q.subscribe().unwrap();
+        for i in 0..7 {
+            q.push(Arc::new(DropCount(&drops, i))).unwrap();
+        }
+        // Two blocks survive, the oldest one only partly filled.
+        assert_eq!(drops.load(Ordering::SeqCst), 4);
+        assert!(sub.recv().is_err());
+        assert_eq!(sub.recv().unwrap().1, 4);
+    }
+    assert_eq!(drops.load(Ordering::SeqCst), 7);
+}
+
+#[test]
+fn mpmc_strings() {
+    const COUNT: usize = 20;
+    const THREADS: usize = 2;
+
+    let q = RingBuffer::<String, 2, 2>::new();
+    let v = (0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+
+    scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                for _ in 0..COUNT {
+                    let n = loop {
+                        if let Some(x) = q.pop() {
+                            break x;
+                        }
+                    };
+                    v[n.parse::<usize>().unwrap()].fetch_add(1, Ordering::SeqCst);
+                }
+            });
+        }
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                for i in 0..COUNT {
+                    while q.push(i.to_string()).is_err() {}
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    for c in v {
+        assert_eq!(c.load(Ordering::SeqCst), THREADS);
+    }
+}
+
+#[test]
+fn deque_steal_strings() {
+    const COUNT: usize = 40;
+
+    let w = Worker::<String, 2, 2>::new();
+    let s = w.stealer();
+    let v = (0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+    let total = AtomicUsize::new(0);
+
+    let record = |x: String| {
+        v[x.parse::<usize>().unwrap()].fetch_add(1, Ordering::SeqCst);
+        total.fetch_add(1, Ordering::SeqCst);
+    };
+    scope(|scope| {
+        scope.spawn(|_| {
+            while total.load(Ordering::SeqCst) < COUNT {
+                if let Steal::Success(batch) = s.steal_batch() {
+                    batch.for_each(record);
+                }
+            }
+        });
+
+        for i in 0..COUNT {
+            let mut x = i.to_string();
+            while let Err(err) = w.push(x) {
+                x = err.into_inner();
+                if let Some(y) = w.pop() {
+                    record(y);
+                }
+            }
+            if i % 3 == 0
+                && let Some(y) = w.pop()
+            {
+                record(y);
+            }
+        }
+        while let Some(y) = w.pop() {
+            record(y);
+        }
+    })
+    .unwrap();
+
+    for c in v {
+        assert_eq!(c.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,130 @@
+// Single-threaded push/pop sequences checked step by step against a `VecDeque` model.
+use bbring::RingBuffer;
+use proptest::prelude::*;
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Push,
+    Pop,
+}
+
+// A `VecDeque` bounded by `capacity()`, plus the one rule retry-new adds on top: a push
+// that has to move on to a new block only succeeds if every item of that block's
+// previous lap has been popped. So a push at a block boundary may be refused although
+// up to `slot_num - 1` slots are free.
+struct Model {
+    items: VecDeque<usize>,
+    capacity: usize,
+    slot_num: usize,
+    pushed: usize,
+}
+
+impl Model {
+    fn push(&mut self, value: usize) -> Result<(), usize> {
+        let len = self.items.len();
+        let opens_block = self.pushed > 0 && self.pushed.is_multiple_of(self.slot_num);
+
+        if len == self.capacity || (opens_block && len > self.capacity - self.slot_num) {
+            return Err(value);
+        }
+        self.items.push_back(value);
+        self.pushed += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<usize> {
+        self.items.pop_front()
+    }
+}
+
+fn run<const BLOCK_NUM: usize, const SLOT_NUM: usize>(ops: &[Op]) -> Result<(), TestCaseError> {
+    let q = RingBuffer::<usize, BLOCK_NUM, SLOT_NUM>::new();
+    let mut model = Model {
+        items: VecDeque::new(),
+        capacity: q.capacity(),
+        slot_num: SLOT_NUM,
+        pushed: 0,
+    };
+
+    for (i, op) in ops.iter().enumerate() {
+        match op {
+            Op::Push => {
+                let got = q.push(i).map_err(|e| e.into_inner());
+                prop_assert_eq!(got, model.push(i), "push at step {}", i);
+            }
+            Op::Pop => prop_assert_eq!(q.pop(), model.pop(), "pop at step {}", i),
+        }
+    }
+
+    prop_assert_eq!(q.snapshot(), Vec::from(model.items.clone()));
+    while let Some(v) = model.pop() {
+        prop_assert_eq!(q.pop(), Some(v));
+    }
+    prop_assert_eq!(q.pop(), None);
+    Ok(())
+}
+
+// Sequences with a random mix of pushes and pops, so that runs of both full and empty
+// queues come up.
+fn ops() -> impl Strategy<Value = Vec<Op>> {
+    (1u8..8).prop_flat_map(|push_weight| {
+        prop::collection::vec(
+            (0u8..8).prop_map(move |x| if x < push_weight { Op::Push } else { Op::Pop }),
+            0..1000,
+        )
+    })
+}
+
+proptest! {
+    #[test]
+    fn model_1x1(ops in ops()) {
+        run::<1, 1>(&ops)?;
+    }
+
+    #[test]
+    fn model_2x1(ops in ops()) {
+        run::<2, 1>(&ops)?;
+    }
+
+    #[test]
+    fn model_1x4(ops in ops()) {
+        run::<1, 4>(&ops)?;
+    }
+
+    #[test]
+    fn model_4x2(ops in ops()) {
+        run::<4, 2>(&ops)?;
+    }
+
+    #[test]
+    fn model_16x16(ops in ops()) {
+        run::<16, 16>(&ops)?;
+    }
+
+    #[test]
+    fn model_3x5(ops in ops()) {
+        run::<3, 5>(&ops)?;
+    }
+
+    #[test]
+    fn model_5x3(ops in ops()) {
+        run::<5, 3>(&ops)?;
+    }
+}
+
+#[test]
+fn push_refused_with_free_slots() {
+    let q = RingBuffer::<usize, 4, 2>::new();
+    for i in 0..8 {
+        q.push(i).unwrap();
+    }
+    assert_eq!(q.pop(), Some(0));
+
+    // One slot is free, but it sits in a block that still holds item 1.
+    assert!(q.push(8).is_err());
+    assert_eq!(q.pop(), Some(1));
+    q.push(8).unwrap();
+    q.push(9).unwrap();
+}
@@ -0,0 +1,86 @@
+#![cfg(all(feature = "numa", target_os = "linux"))]
+
+use bbring::numa::{NumaRing, current_node, node_count};
+use bbring::{PopError, RingBuffer};
+use crossbeam_utils::thread::scope;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[test]
+fn nodes() {
+    let count = node_count();
+    assert!(count >= 1);
+    assert!(current_node() < count);
+}
+
+#[test]
+fn on_node() {
+    // Node 0 always exists, the last one never does and must not get in the way.
+    for node in [0, node_count(), usize::MAX] {
+        let q = RingBuffer::<usize, 4, 1024>::new_boxed_on_node(node);
+        for lap in 0..3 {
+            for i in 0..4096 {
+                q.push(lap * 4096 + i).unwrap();
+            }
+            for i in 0..4096 {
+                assert_eq!(q.pop(), Some(lap * 4096 + i));
+            }
+        }
+    }
+}
+
+#[test]
+fn numa_ring() {
+    let q = NumaRing::<i32, 4, 2>::new();
+    assert_eq!(q.shards(), node_count());
+    assert_eq!(q.capacity(), node_count() * 8);
+
+    // One thread stays in order unless it migrates, which a single push and pop can not
+    // tell apart from staying put.
+    q.push(1).unwrap();
+    assert_eq!(q.pop(), Some(1));
+    assert_eq!(q.try_pop(), Err(PopError::Empty));
+
+    q.push(2).unwrap();
+    assert!(q.close());
+    assert!(q.is_closed());
+    assert!(q.push(3).is_err());
+    assert_eq!(q.try_pop(), Ok(2));
+    assert_eq!(q.try_pop(), Err(PopError::Closed));
+}
+
+#[test]
+fn numa_ring_mpmc() {
+    const COUNT: usize = 25_000;
+    const THREADS: usize = 4;
+
+    let q = NumaRing::<usize, 8, 64>::new();
+    let v = (0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+
+    scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                for _ in 0..COUNT {
+                    let n = loop {
+                        if let Some(x) = q.pop() {
+                            break x;
+                        }
+                    };
+                    v[n].fetch_add(1, Ordering::SeqCst);
+                }
+            });
+        }
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                for i in 0..COUNT {
+                    while q.push(i).is_err() {}
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    for c in v {
+        assert_eq!(c.load(Ordering::SeqCst), THREADS);
+    }
+}
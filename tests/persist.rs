@@ -0,0 +1,251 @@
+#![cfg(all(feature = "persist", unix, target_has_atomic = "64"))]
+
+use bbring::persist::PersistentRing;
+
+use std::env;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::Duration;
+
+type Ring = PersistentRing<(u64, u64), 4, 8>;
+
+// Set in the child processes the tests below start and kill.
+const CHILD: &str = "BBRING_PERSIST_CHILD";
+
+fn temp(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("bbring-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+// Runs the test `name` again in a child process, which takes the other branch of it.
+fn spawn(name: &str, path: &PathBuf) -> Child {
+    Command::new(env::current_exe().unwrap())
+        .args([name, "--exact", "--nocapture"])
+        .env(CHILD, path)
+        .spawn()
+        .unwrap()
+}
+
+// Drains the ring, checking that what each producer pushed comes out in order and
+// without gaps: a crash can only cut off a producer's last push and a consumer's last
+// pop, which are at the ends.
+fn drain_in_order(q: &Ring) -> usize {
+    let mut last = [None::<u64>; 2];
+    let mut count = 0;
+    while let Some((producer, i)) = q.pop() {
+        let last = &mut last[producer as usize];
+        if let Some(prev) = *last {
+            assert_eq!(i, prev + 1);
+        }
+        *last = Some(i);
+        count += 1;
+    }
+    count
+}
+
+#[test]
+fn reopen() {
+    let path = temp("reopen");
+    {
+        let q = Ring::open(&path).unwrap();
+        for i in 0..20 {
+            q.push((0, i)).unwrap();
+        }
+        for i in 0..5 {
+            assert_eq!(q.pop(), Some((0, i)));
+        }
+        q.flush().unwrap();
+
+        let err = Ring::open(&path).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    }
+
+    let q = Ring::open(&path).unwrap();
+    assert_eq!(q.cut_off(), 0);
+    for i in 5..20 {
+        assert_eq!(q.pop(), Some((0, i)));
+    }
+    assert_eq!(q.pop(), None);
+
+    // Closing is kept as well.
+    q.push((0, 20)).unwrap();
+    assert!(q.close());
+    drop(q);
+    let q = Ring::open(&path).unwrap();
+    assert!(q.is_closed());
+    assert!(q.push((0, 21)).is_err());
+    assert_eq!(q.pop(), Some((0, 20)));
+    drop(q);
+
+    let err = PersistentRing::<(u64, u64), 8, 8>::open(&path)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn killed_mid_push() {
+    if let Some(path) = env::var_os(CHILD) {
+        // Two producers and a consumer, running until the parent kills the process.
+        let q = Ring::open(path).unwrap();
+        let q = &q;
+        thread::scope(|scope| {
+            for producer in 0..2 {
+                scope.spawn(move || {
+                    for i in 0.. {
+                        while q.push((producer, i)).is_err() {}
+                    }
+                });
+            }
+            loop {
+                q.pop();
+            }
+        });
+    }
+
+    let path = temp("killed");
+    for round in 0..20 {
+        let mut child = spawn("killed_mid_push", &path);
+        thread::sleep(Duration::from_millis(50 + round * 5));
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let q = Ring::open(&path).unwrap();
+        drain_in_order(&q);
+
+        // The repaired ring keeps working.
+        for lap in 0..3 {
+            for i in 0..16 {
+                q.push((0, lap * 16 + i)).unwrap();
+            }
+            assert_eq!(drain_in_order(&q), 16);
+        }
+        drop(q);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+// Pushes into a new ring at `path` until the tenth push reaches `abort_at`.
+#[cfg(feature = "step-hook")]
+fn push_until_abort(path: &std::ffi::OsStr, abort_at: bbring::hook::Step) {
+    let q = Ring::open(path).unwrap();
+    let mut reached = 0;
+    bbring::hook::set_step_hook(move |step| {
+        if step == abort_at {
+            reached += 1;
+            if reached == 10 {
+                std::process::abort();
+            }
+        }
+    });
+    for i in 0.. {
+        q.push((0, i)).unwrap();
+        if i % 3 == 0 {
+            q.pop().unwrap();
+        }
+    }
+}
+
+#[cfg(feature = "step-hook")]
+#[test]
+fn aborted_before_write() {
+    if let Some(path) = env::var_os(CHILD) {
+        push_until_abort(&path, bbring::hook::Step::CommitWrite);
+    }
+
+    let path = temp("before-write");
+    let mut child = spawn("aborted_before_write", &path);
+    assert!(!child.wait().unwrap().success());
+
+    // The slot of the tenth push was taken but never written.
+    let q = Ring::open(&path).unwrap();
+    assert_eq!(q.cut_off(), 1);
+    for i in 3..9 {
+        assert_eq!(q.pop(), Some((0, i)));
+    }
+    assert_eq!(q.pop(), None);
+    q.push((0, 10)).unwrap();
+    assert_eq!(q.pop(), Some((0, 10)));
+    drop(q);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "step-hook")]
+#[test]
+fn aborted_before_stamp() {
+    if let Some(path) = env::var_os(CHILD) {
+        push_until_abort(&path, bbring::hook::Step::CommitStamp);
+    }
+
+    let path = temp("before-stamp");
+    let mut child = spawn("aborted_before_stamp", &path);
+    assert!(!child.wait().unwrap().success());
+
+    // The item of the tenth push is in its slot, but without a stamp it may be torn.
+    let q = Ring::open(&path).unwrap();
+    assert_eq!(q.cut_off(), 1);
+    for i in 3..9 {
+        assert_eq!(q.pop(), Some((0, i)));
+    }
+    assert_eq!(q.pop(), None);
+    q.push((0, 10)).unwrap();
+    assert_eq!(q.pop(), Some((0, 10)));
+    drop(q);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "step-hook")]
+#[test]
+fn aborted_before_commit() {
+    if let Some(path) = env::var_os(CHILD) {
+        push_until_abort(&path, bbring::hook::Step::CommitPublish);
+    }
+
+    let path = temp("before-commit");
+    let mut child = spawn("aborted_before_commit", &path);
+    assert!(!child.wait().unwrap().success());
+
+    // The tenth push was written but never counted, and is kept.
+    let q = Ring::open(&path).unwrap();
+    assert_eq!(q.cut_off(), 0);
+    for i in 3..10 {
+        assert_eq!(q.pop(), Some((0, i)));
+    }
+    assert_eq!(q.pop(), None);
+    q.push((0, 10)).unwrap();
+    assert_eq!(q.pop(), Some((0, 10)));
+    drop(q);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "step-hook")]
+#[test]
+fn aborted_while_creating() {
+    if let Some(path) = env::var_os(CHILD) {
+        bbring::hook::set_step_hook(|step| {
+            if step == bbring::hook::Step::CreateMagic {
+                std::process::abort();
+            }
+        });
+        Ring::open(path).unwrap();
+    }
+
+    let path = temp("creating");
+    let mut child = spawn("aborted_while_creating", &path);
+    assert!(!child.wait().unwrap().success());
+
+    // The file has its size and geometry but no magic, so it is started over.
+    let q = Ring::open(&path).unwrap();
+    assert_eq!(q.cut_off(), 0);
+    assert_eq!(q.pop(), None);
+    q.push((0, 1)).unwrap();
+    drop(q);
+    let q = Ring::open(&path).unwrap();
+    assert_eq!(q.pop(), Some((0, 1)));
+    drop(q);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,101 @@
+use bbring::{ObjectPool, Pooled};
+use crossbeam_utils::thread::scope;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[test]
+fn reuses_objects() {
+    let created = Arc::new(AtomicUsize::new(0));
+    let pool = {
+        let created = created.clone();
+        ObjectPool::new(2, 2, move || {
+            created.fetch_add(1, Ordering::SeqCst);
+            Vec::<u8>::with_capacity(64)
+        })
+    };
+    assert_eq!(pool.capacity(), 4);
+
+    let mut buf = pool.get();
+    buf.extend_from_slice(b"hello");
+    let ptr = buf.as_ptr();
+    drop(buf);
+
+    // The same buffer comes back, still holding its contents without a reset hook.
+    let buf = pool.get();
+    assert_eq!(buf.as_ptr(), ptr);
+    assert_eq!(&buf[..], b"hello");
+    assert_eq!(created.load(Ordering::SeqCst), 1);
+
+    let other = pool.get();
+    assert_ne!(other.as_ptr(), ptr);
+    assert_eq!(created.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn reset_hook() {
+    let pool = ObjectPool::new(2, 2, Vec::<u8>::new).with_reset(Vec::clear);
+
+    let mut buf = pool.get();
+    buf.extend_from_slice(b"stale");
+    drop(buf);
+    assert!(pool.get().is_empty());
+}
+
+#[test]
+fn full_pool_drops() {
+    let dropped = Arc::new(AtomicUsize::new(0));
+
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let pool = {
+        let dropped = dropped.clone();
+        ObjectPool::new(2, 2, move || Counted(dropped.clone()))
+    };
+
+    let held = (0..6).map(|_| pool.get()).collect::<Vec<_>>();
+    drop(held);
+    // Four fit back, the rest are dropped.
+    assert_eq!(dropped.load(Ordering::SeqCst), 2);
+
+    let kept = Pooled::into_inner(pool.get());
+    drop(pool);
+    assert_eq!(dropped.load(Ordering::SeqCst), 5);
+    drop(kept);
+    assert_eq!(dropped.load(Ordering::SeqCst), 6);
+}
+
+#[test]
+fn concurrent() {
+    #[cfg(miri)]
+    const COUNT: usize = 50;
+    #[cfg(not(miri))]
+    const COUNT: usize = 10_000;
+    const THREADS: usize = 4;
+
+    let pool = ObjectPool::new(4, 4, || Box::new(AtomicUsize::new(0)));
+    let uses = AtomicUsize::new(0);
+
+    scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                for _ in 0..COUNT {
+                    // Nobody else may hold the object while we do.
+                    let obj = pool.get();
+                    assert_eq!(obj.swap(1, Ordering::SeqCst), 0);
+                    uses.fetch_add(1, Ordering::SeqCst);
+                    obj.store(0, Ordering::SeqCst);
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    assert_eq!(uses.load(Ordering::SeqCst), THREADS * COUNT);
+}
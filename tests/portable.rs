@@ -0,0 +1,33 @@
+#![cfg(feature = "portable-atomic")]
+
+use bbring::{PopError, PushError, RingBuffer};
+use portable_atomic::{AtomicU32, AtomicU64};
+
+#[test]
+fn laps_and_close() {
+    let q = RingBuffer::<u32, 4, 8, AtomicU32>::new();
+    let w = RingBuffer::<u32, 3, 5, AtomicU64>::new_boxed();
+    for lap in 0..1000 {
+        for i in 0..32 {
+            q.push(lap * 32 + i).unwrap();
+        }
+        assert_eq!(q.push(0), Err(PushError::Full(0)));
+        for i in 0..15 {
+            w.push(lap * 15 + i).unwrap();
+        }
+        for i in 0..32 {
+            assert_eq!(q.pop(), Some(lap * 32 + i));
+        }
+        for i in 0..15 {
+            assert_eq!(w.pop(), Some(lap * 15 + i));
+        }
+        assert_eq!(q.pop(), None);
+        assert_eq!(w.pop(), None);
+    }
+
+    q.push(1).unwrap();
+    assert!(q.close());
+    assert_eq!(q.push(2), Err(PushError::Closed(2)));
+    assert_eq!(q.pop(), Some(1));
+    assert_eq!(q.try_pop(), Err(PopError::Closed));
+}
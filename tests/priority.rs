@@ -0,0 +1,134 @@
+use bbring::PriorityRing;
+use crossbeam_utils::thread::scope;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[test]
+fn smoke() {
+    let q = PriorityRing::<i32, 3, 4, 2>::new();
+    assert!(q.is_empty());
+    assert!(q.pop().is_none());
+
+    q.push(0, 1).unwrap();
+    q.push(2, 2).unwrap();
+    q.push(1, 3).unwrap();
+    q.push(2, 4).unwrap();
+    q.push(0, 5).unwrap();
+
+    assert_eq!(q.pop(), Some(2));
+    assert_eq!(q.pop(), Some(4));
+    assert_eq!(q.pop(), Some(3));
+    assert_eq!(q.pop(), Some(1));
+    assert_eq!(q.pop(), Some(5));
+    assert!(q.pop().is_none());
+    assert!(q.is_empty());
+}
+
+#[test]
+fn starvation_guard() {
+    let q = PriorityRing::<i32, 2, 4, 4>::with_starvation_guard(3);
+    for i in 0..8 {
+        q.push(1, i).unwrap();
+    }
+    q.push(0, 100).unwrap();
+    q.push(0, 101).unwrap();
+
+    let order = (0..10).map(|_| q.pop().unwrap()).collect::<Vec<_>>();
+    assert_eq!(order, vec![0, 1, 2, 100, 3, 4, 5, 101, 6, 7]);
+    assert!(q.pop().is_none());
+}
+
+#[test]
+fn starvation_guard_rotates() {
+    let q = PriorityRing::<i32, 3, 4, 8>::with_starvation_guard(2);
+    for i in 0..8 {
+        q.push(2, i).unwrap();
+    }
+    for i in 100..106 {
+        q.push(1, i).unwrap();
+    }
+    q.push(0, 200).unwrap();
+    q.push(0, 201).unwrap();
+
+    // The guard serves level 1 and level 0 in turn, not just the next level down.
+    let order = (0..16).map(|_| q.pop().unwrap()).collect::<Vec<_>>();
+    assert_eq!(
+        order,
+        vec![
+            0, 1, 100, 2, 3, 200, 4, 5, 101, 6, 7, 201, 102, 103, 104, 105
+        ]
+    );
+    assert!(q.pop().is_none());
+
+    // Level 0 still gets through with both levels above it pushed to after every pop.
+    q.push(0, 300).unwrap();
+    let mut pops = 0;
+    loop {
+        q.push(2, 1).unwrap();
+        q.push(1, 2).unwrap();
+        pops += 1;
+        if q.pop() == Some(300) {
+            break;
+        }
+    }
+    assert!(pops <= 6, "level 0 waited {pops} pops");
+}
+
+#[test]
+fn starvation_guard_all_levels() {
+    const LEVELS: usize = usize::BITS as usize;
+
+    let q = PriorityRing::<usize, LEVELS, 2, 4>::with_starvation_guard(1);
+    for i in 0..3 {
+        q.push(LEVELS - 1, i).unwrap();
+    }
+    q.push(0, 100).unwrap();
+    q.push(1, 101).unwrap();
+
+    let order = (0..5).map(|_| q.pop().unwrap()).collect::<Vec<_>>();
+    assert_eq!(order, vec![0, 101, 1, 100, 2]);
+    assert!(q.pop().is_none());
+}
+
+#[test]
+fn mpmc() {
+    #[cfg(miri)]
+    const COUNT: usize = 50;
+    #[cfg(not(miri))]
+    const COUNT: usize = 2_000;
+    const THREADS: usize = 4;
+    const LEVELS: usize = 4;
+
+    let q = PriorityRing::<usize, LEVELS, 4, 2>::new();
+    let v = (0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+
+    scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                for _ in 0..COUNT {
+                    let n = loop {
+                        if let Some(x) = q.pop() {
+                            break x;
+                        }
+                    };
+                    v[n].fetch_add(1, Ordering::SeqCst);
+                }
+            });
+        }
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                for i in 0..COUNT {
+                    while q.push(i % LEVELS, i).is_err() {}
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    // Drained levels keep their bits until a pop finds them empty.
+    assert!(q.pop().is_none());
+    assert!(q.is_empty());
+    for c in v {
+        assert_eq!(c.load(Ordering::SeqCst), THREADS);
+    }
+}
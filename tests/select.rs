@@ -0,0 +1,129 @@
+use bbring::{DynRingBuffer, PopError, RingBuffer, Select, SelectTimeoutError};
+use crossbeam_utils::thread::scope;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+#[test]
+fn smoke() {
+    let control = RingBuffer::<&str, 4, 2>::new();
+    let data = DynRingBuffer::new(4, 2);
+
+    let mut sel = Select::new();
+    assert_eq!(sel.recv(&control), 0);
+    assert_eq!(sel.recv(&data), 1);
+
+    assert_eq!(sel.try_select(), Err(PopError::Empty));
+    data.push("payload").unwrap();
+    assert_eq!(sel.select(), Some((1, "payload")));
+    control.push("stop").unwrap();
+    assert_eq!(sel.select(), Some((0, "stop")));
+}
+
+#[test]
+fn fair() {
+    let a = RingBuffer::<i32, 4, 4>::new();
+    let b = RingBuffer::<i32, 4, 4>::new();
+    for i in 0..8 {
+        a.push(i).unwrap();
+        b.push(100 + i).unwrap();
+    }
+
+    let mut sel = Select::new();
+    sel.recv(&a);
+    sel.recv(&b);
+
+    // Both stay ready, so they take turns.
+    let served = (0..16).map(|_| sel.select().unwrap()).collect::<Vec<_>>();
+    for (i, pair) in served.chunks(2).enumerate() {
+        assert_eq!(pair, [(0, i as i32), (1, 100 + i as i32)]);
+    }
+}
+
+#[test]
+fn closed() {
+    let a = RingBuffer::<i32, 4, 2>::new();
+    let b = RingBuffer::<i32, 4, 2>::new();
+    let mut sel = Select::new();
+    sel.recv(&a);
+    sel.recv(&b);
+
+    a.push(1).unwrap();
+    a.close();
+    assert_eq!(sel.select(), Some((0, 1)));
+
+    // A drained queue is skipped while the other one is still open.
+    assert_eq!(sel.try_select(), Err(PopError::Empty));
+    b.push(2).unwrap();
+    b.close();
+    assert_eq!(sel.select(), Some((1, 2)));
+    assert_eq!(sel.select(), None);
+    assert_eq!(
+        sel.select_timeout(Duration::from_secs(10)),
+        Err(SelectTimeoutError::Closed)
+    );
+
+    let mut empty = Select::<i32>::new();
+    assert_eq!(empty.select(), None);
+}
+
+#[test]
+fn timeout() {
+    let q = RingBuffer::<i32, 4, 2>::new();
+    let mut sel = Select::new();
+    sel.recv(&q);
+
+    let start = Instant::now();
+    assert_eq!(
+        sel.select_timeout(Duration::from_millis(20)),
+        Err(SelectTimeoutError::Timeout)
+    );
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    q.push(7).unwrap();
+    assert_eq!(sel.select_timeout(Duration::ZERO), Ok((0, 7)));
+}
+
+#[test]
+fn blocking() {
+    const COUNT: usize = 2_000;
+
+    let control = RingBuffer::<usize, 4, 2>::new();
+    let data = RingBuffer::<usize, 4, 2>::new();
+    let seen = AtomicUsize::new(0);
+
+    scope(|scope| {
+        scope.spawn(|_| {
+            let mut sel = Select::new();
+            sel.recv(&control);
+            sel.recv(&data);
+
+            let mut next = 0;
+            loop {
+                match sel.select().unwrap() {
+                    (0, _) => break,
+                    (_, n) => {
+                        assert_eq!(n, next);
+                        next += 1;
+                    }
+                }
+            }
+            // The stop signal may overtake data that is still queued.
+            while let Some(n) = data.pop() {
+                assert_eq!(n, next);
+                next += 1;
+            }
+            seen.store(next, Ordering::SeqCst);
+        });
+
+        scope.spawn(|_| {
+            for i in 0..COUNT {
+                while data.push(i).is_err() {}
+            }
+            control.push(0).unwrap();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(seen.load(Ordering::SeqCst), COUNT);
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "serde")]
+
+use bbring::RingBuffer;
+
+#[test]
+fn snapshot() {
+    let q = RingBuffer::<i32, 4, 2>::new();
+    assert!(q.snapshot().is_empty());
+
+    // Walk the cursors past a lap so the snapshot starts in the middle of a block.
+    for i in 0..8 {
+        q.push(i).unwrap();
+    }
+    for i in 0..5 {
+        assert_eq!(q.pop(), Some(i));
+    }
+    for i in 8..10 {
+        q.push(i).unwrap();
+    }
+
+    assert_eq!(q.snapshot(), vec![5, 6, 7, 8, 9]);
+    // Taking a snapshot does not consume anything.
+    assert_eq!(q.snapshot(), vec![5, 6, 7, 8, 9]);
+    assert_eq!(q.pop(), Some(5));
+}
+
+#[test]
+fn roundtrip() {
+    let q = RingBuffer::<String, 4, 2>::new();
+    for i in 0..6 {
+        q.push(i.to_string()).unwrap();
+    }
+    q.pop().unwrap();
+
+    let json = serde_json::to_string(&q).unwrap();
+    assert_eq!(json, r#"["1","2","3","4","5"]"#);
+
+    let restored: RingBuffer<String, 4, 2> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.snapshot(), q.snapshot());
+    for i in 1..6 {
+        assert_eq!(restored.pop(), Some(i.to_string()));
+    }
+    assert!(restored.pop().is_none());
+}
+
+#[test]
+fn reject_oversized() {
+    let full = (0..8).collect::<Vec<i32>>();
+    let q: RingBuffer<i32, 4, 2> =
+        serde_json::from_str(&serde_json::to_string(&full).unwrap()).unwrap();
+    assert_eq!(q.snapshot(), full);
+
+    let too_long = (0..9).collect::<Vec<i32>>();
+    let err =
+        serde_json::from_str::<RingBuffer<i32, 4, 2>>(&serde_json::to_string(&too_long).unwrap());
+    assert!(err.is_err());
+}
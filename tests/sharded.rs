@@ -0,0 +1,75 @@
+use bbring::{PopError, ShardedRing};
+use crossbeam_utils::thread::scope;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[test]
+fn smoke() {
+    let q = ShardedRing::<i32, 4, 2>::new(4);
+    assert_eq!(q.shards(), 4);
+    assert_eq!(q.capacity(), 32);
+
+    // A single thread always lands in the same shard.
+    for i in 0..8 {
+        q.push(i).unwrap();
+    }
+    assert!(q.push(8).is_err());
+    for i in 0..8 {
+        assert_eq!(q.pop(), Some(i));
+    }
+    assert!(q.pop().is_none());
+
+    q.push(9).unwrap();
+    assert!(q.close());
+    assert!(q.is_closed());
+    assert!(q.push(10).is_err());
+    assert_eq!(q.try_pop(), Ok(9));
+    assert_eq!(q.try_pop(), Err(PopError::Closed));
+}
+
+#[test]
+fn mpmc() {
+    #[cfg(miri)]
+    const COUNT: usize = 50;
+    #[cfg(not(miri))]
+    const COUNT: usize = 2_000;
+    const THREADS: usize = 4;
+
+    let q = ShardedRing::<usize, 4, 2>::new(THREADS);
+    let v = (0..THREADS * COUNT)
+        .map(|_| AtomicUsize::new(0))
+        .collect::<Vec<_>>();
+
+    scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                // Items of one producer must come out in the order they went in.
+                let mut last = [None; THREADS];
+                for _ in 0..COUNT {
+                    let n = loop {
+                        if let Some(x) = q.pop() {
+                            break x;
+                        }
+                    };
+                    let (t, i) = (n / COUNT, n % COUNT);
+                    assert!(last[t] < Some(i));
+                    last[t] = Some(i);
+                    v[n].fetch_add(1, Ordering::SeqCst);
+                }
+            });
+        }
+        for t in 0..THREADS {
+            let q = &q;
+            scope.spawn(move |_| {
+                for i in 0..COUNT {
+                    while q.push(t * COUNT + i).is_err() {}
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    for c in v {
+        assert_eq!(c.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,8 +1,9 @@
 // modified from crossbeam
-use bbring::RingBuffer;
+use bbring::{ConfigError, DynRingBuffer, Geometry, PopError, PushError, RingBuffer, SlotLayout};
 use crossbeam_utils::thread::scope;
 
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::mem;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 
 #[test]
 fn smoke() {
@@ -159,3 +160,354 @@ fn mpmc() {
         assert_eq!(c.load(Ordering::SeqCst), THREADS);
     }
 }
+
+// Has three producers push `0..COUNT` each while three consumers pop, and checks that
+// every item came out once per producer.
+fn check_mpmc<R: Sync>(q: R, push: fn(&R, usize) -> bool, pop: fn(&R) -> Option<usize>) {
+    #[cfg(miri)]
+    const COUNT: usize = 50;
+    #[cfg(not(miri))]
+    const COUNT: usize = 5_000;
+    const THREADS: usize = 3;
+
+    let q = &q;
+    let v = (0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+
+    scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                for _ in 0..COUNT {
+                    let n = loop {
+                        if let Some(x) = pop(q) {
+                            break x;
+                        }
+                    };
+                    v[n].fetch_add(1, Ordering::SeqCst);
+                }
+            });
+        }
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                for i in 0..COUNT {
+                    while !push(q, i) {}
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    for c in v {
+        assert_eq!(c.load(Ordering::SeqCst), THREADS);
+    }
+}
+
+#[test]
+fn close() {
+    let q = RingBuffer::<i32, 4, 2>::new();
+    q.push(1).unwrap();
+    q.push(2).unwrap();
+    assert!(!q.is_closed());
+
+    assert!(q.close());
+    assert!(!q.close());
+    assert!(q.is_closed());
+    assert_eq!(q.push(3), Err(PushError::Closed(3)));
+
+    assert_eq!(q.try_pop(), Ok(1));
+    assert_eq!(q.try_pop(), Ok(2));
+    assert_eq!(q.try_pop(), Err(PopError::Closed));
+    assert!(q.pop().is_none());
+
+    let q = RingBuffer::<i32, 4, 2>::new();
+    for i in 0..8 {
+        q.push(i).unwrap();
+    }
+    assert_eq!(q.push(8), Err(PushError::Full(8)));
+    assert_eq!(q.try_pop(), Ok(0));
+    q.close();
+    for i in 1..8 {
+        assert_eq!(q.try_pop(), Ok(i));
+    }
+    assert_eq!(q.try_pop(), Err(PopError::Closed));
+}
+
+#[test]
+fn close_mpmc() {
+    #[cfg(miri)]
+    const COUNT: usize = 50;
+    #[cfg(not(miri))]
+    const COUNT: usize = 2_000;
+    const THREADS: usize = 4;
+
+    let q = RingBuffer::<usize, 4, 2>::new();
+    let pushed = (0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+    let popped = (0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+
+    scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|_| {
+                loop {
+                    match q.try_pop() {
+                        Ok(n) => {
+                            popped[n].fetch_add(1, Ordering::SeqCst);
+                        }
+                        Err(PopError::Empty) => {}
+                        Err(PopError::Closed) => break,
+                    }
+                }
+            });
+        }
+        for t in 0..THREADS {
+            let (q, pushed) = (&q, &pushed);
+            scope.spawn(move |_| {
+                for (i, count) in pushed.iter().enumerate() {
+                    if t == 0 && i == COUNT / 2 {
+                        q.close();
+                    }
+                    loop {
+                        match q.push(i) {
+                            Ok(()) => {
+                                count.fetch_add(1, Ordering::SeqCst);
+                                break;
+                            }
+                            Err(PushError::Full(_)) => {}
+                            Err(PushError::Closed(_)) => return,
+                        }
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    for (p, c) in pushed.iter().zip(popped.iter()) {
+        assert_eq!(p.load(Ordering::SeqCst), c.load(Ordering::SeqCst));
+    }
+}
+
+#[test]
+fn huge_values() {
+    // 16 MiB of slots, far more than the stack of a test thread.
+    type Page = [u8; 64 * 1024];
+
+    let q = RingBuffer::<Page, 64, 4>::new_boxed();
+    for lap in 0..2 {
+        for i in 0..q.capacity() {
+            q.push([(lap + i) as u8; 64 * 1024]).unwrap();
+        }
+        for i in 0..q.capacity() {
+            let page = q.pop().unwrap();
+            assert!(page.iter().all(|&b| b == (lap + i) as u8));
+        }
+    }
+    assert!(q.pop().is_none());
+}
+
+#[test]
+fn dyn_ring() {
+    let q = DynRingBuffer::new(4, 2);
+    assert_eq!((q.block_num(), q.slot_num(), q.capacity()), (4, 2, 8));
+
+    // Same behaviour as the `RingBuffer` it mirrors, two laps round.
+    for lap in 0..2 {
+        for i in 0..8 {
+            q.push(lap * 8 + i).unwrap();
+        }
+        assert!(matches!(q.push(99), Err(PushError::Full(99))));
+        assert_eq!(q.snapshot(), (lap * 8..lap * 8 + 8).collect::<Vec<_>>());
+        for i in 0..8 {
+            assert_eq!(q.pop(), Some(lap * 8 + i));
+        }
+    }
+
+    q.push(1).unwrap();
+    assert!(q.close());
+    assert_eq!(q.try_pop(), Ok(1));
+    assert_eq!(q.try_pop(), Err(PopError::Closed));
+}
+
+#[test]
+fn dyn_ring_config() {
+    assert!(DynRingBuffer::<i32>::try_new(4, 2).is_ok());
+    assert!(DynRingBuffer::<i32>::try_new(1, 1).is_ok());
+    assert!(DynRingBuffer::<i32>::try_new(3, 5).is_ok());
+
+    let err = |block_num, slot_num| DynRingBuffer::<i32>::try_new(block_num, slot_num).err();
+    assert_eq!(err(0, 2), Some(ConfigError::BlockNum));
+    assert_eq!(err(4, 0), Some(ConfigError::SlotNum));
+    assert_eq!(err(4, 1 << (usize::BITS - 1)), Some(ConfigError::TooLarge));
+    assert_eq!(
+        err(1 << (usize::BITS / 2), 1 << (usize::BITS / 2)),
+        Some(ConfigError::TooLarge)
+    );
+}
+
+#[test]
+#[should_panic(expected = "SLOT_NUM must be non-zero")]
+fn dyn_ring_bad_config() {
+    DynRingBuffer::<i32>::new(4, 0);
+}
+
+#[test]
+fn odd_geometry() {
+    let q = RingBuffer::<usize, 3, 5>::new();
+    assert_eq!(q.capacity(), 15);
+    let d = DynRingBuffer::new(3, 5);
+    assert_eq!(d.capacity(), 15);
+
+    // Several laps, so the versions above the rounded up index field get exercised.
+    for lap in 0..4 {
+        for i in 0..15 {
+            q.push(lap * 15 + i).unwrap();
+            d.push(lap * 15 + i).unwrap();
+        }
+        assert!(q.push(99).is_err());
+        assert!(d.push(99).is_err());
+        for i in 0..15 {
+            assert_eq!(q.pop(), Some(lap * 15 + i));
+            assert_eq!(d.pop(), Some(lap * 15 + i));
+        }
+    }
+}
+
+#[test]
+fn odd_geometry_mpmc() {
+    check_mpmc(
+        RingBuffer::<usize, 3, 5>::new(),
+        |q, i| q.push(i).is_ok(),
+        |q| q.pop(),
+    );
+}
+
+#[test]
+fn geometry_for_capacity() {
+    for capacity in [1, 7, 100, 1000, 4096] {
+        for threads in [1, 4, 10, 64] {
+            let g = Geometry::for_capacity::<u64>(capacity, threads).unwrap();
+            assert!(g.block_num >= 2, "{g}");
+            assert!((g.block_num - 1) * g.slot_num >= capacity, "{g}");
+            assert_eq!(g.capacity(), g.block_num * g.slot_num);
+
+            // Slots come in whole cache lines, so small items get larger blocks.
+            let small = Geometry::for_capacity::<u8>(capacity, threads).unwrap();
+            assert!(small.slot_num >= g.slot_num, "{small} vs {g}");
+        }
+    }
+
+    let big = Geometry::for_capacity::<[u8; 4096]>(4096, 10).unwrap();
+    assert_eq!(big.block_num, 21);
+
+    // Rounding up to whole blocks and cache lines must not overflow.
+    for capacity in [usize::MAX - 1, usize::MAX] {
+        assert_eq!(
+            Geometry::for_capacity::<u8>(capacity, 1),
+            Err(ConfigError::TooLarge)
+        );
+    }
+    assert!(Geometry::for_capacity::<u64>(100, usize::MAX).is_ok());
+}
+
+#[test]
+fn with_capacity_always_fits() {
+    const CAPACITY: usize = 100;
+
+    let q = DynRingBuffer::<usize>::with_capacity(CAPACITY, 4);
+    assert_eq!(
+        q.geometry(),
+        Geometry::for_capacity::<usize>(CAPACITY, 4).unwrap()
+    );
+
+    // Whatever the blocks look like, a push below `CAPACITY` queued items goes through.
+    let mut len = 0;
+    let mut next = 0;
+    for round in 0..50 {
+        while len < CAPACITY {
+            q.push(next).unwrap();
+            next += 1;
+            len += 1;
+        }
+        for _ in 0..=round % CAPACITY {
+            q.pop().unwrap();
+            len -= 1;
+        }
+    }
+}
+
+#[test]
+fn interleaved_layout() {
+    // Enough `u64`s for a couple of cache lines and a few left over, which stay in place.
+    let q = RingBuffer::<u64, 3, 40>::with_layout(SlotLayout::Interleaved);
+    let d = DynRingBuffer::<u64>::with_layout(3, 40, SlotLayout::Interleaved);
+    let b = RingBuffer::<u64, 3, 40>::new_boxed_with_layout(SlotLayout::Interleaved);
+
+    for lap in 0..4 {
+        for i in 0..100 {
+            q.push(lap * 100 + i).unwrap();
+            d.push(lap * 100 + i).unwrap();
+            b.push(lap * 100 + i).unwrap();
+        }
+        assert_eq!(
+            q.snapshot(),
+            (lap * 100..lap * 100 + 100).collect::<Vec<_>>()
+        );
+        assert_eq!(d.snapshot(), q.snapshot());
+        assert_eq!(b.snapshot(), q.snapshot());
+        for i in 0..100 {
+            assert_eq!(q.pop(), Some(lap * 100 + i));
+            assert_eq!(d.pop(), Some(lap * 100 + i));
+            assert_eq!(b.pop(), Some(lap * 100 + i));
+        }
+    }
+}
+
+#[test]
+fn interleaved_layout_mpmc() {
+    check_mpmc(
+        DynRingBuffer::<usize>::with_layout(4, 64, SlotLayout::Interleaved),
+        |q, i| q.push(i).is_ok(),
+        |q| q.pop(),
+    );
+}
+
+#[test]
+fn narrow_index() {
+    type Narrow = RingBuffer<u32, 4, 8, AtomicU32>;
+    assert!(mem::size_of::<Narrow>() <= mem::size_of::<RingBuffer<u32, 4, 8>>());
+
+    let q = Narrow::new();
+    for lap in 0..1000 {
+        for i in 0..32 {
+            q.push(lap * 32 + i).unwrap();
+        }
+        assert_eq!(q.push(0), Err(PushError::Full(0)));
+        for i in 0..32 {
+            assert_eq!(q.pop(), Some(lap * 32 + i));
+        }
+        assert_eq!(q.pop(), None);
+    }
+
+    q.push(1).unwrap();
+    assert!(q.close());
+    assert_eq!(q.push(2), Err(PushError::Closed(2)));
+    assert_eq!(q.pop(), Some(1));
+    assert_eq!(q.try_pop(), Err(PopError::Closed));
+}
+
+#[test]
+fn narrow_index_mpmc() {
+    check_mpmc(
+        RingBuffer::<usize, 3, 5, AtomicU32>::new(),
+        |q, i| q.push(i).is_ok(),
+        |q| q.pop(),
+    );
+}
+
+#[cfg(feature = "portable-atomic")]
+#[test]
+fn portable_index_mpmc() {
+    check_mpmc(
+        RingBuffer::<usize, 3, 5, portable_atomic::AtomicU64>::new(),
+        |q, i| q.push(i).is_ok(),
+        |q| q.pop(),
+    );
+}
@@ -0,0 +1,161 @@
+#![cfg(feature = "tokio")]
+
+use bbring::tokio::{TryRecvError, TrySendError, channel};
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[tokio::test]
+async fn smoke() {
+    let (tx, mut rx) = channel(4);
+    tx.send(7).await.unwrap();
+    assert_eq!(rx.recv().await, Some(7));
+    tx.try_send(8).unwrap();
+    assert_eq!(rx.try_recv(), Ok(8));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[tokio::test]
+async fn exact_capacity() {
+    for cap in [1, 2, 3, 5, 8, 100] {
+        let (tx, mut rx) = channel(cap);
+        for i in 0..cap {
+            tx.try_send(i).unwrap();
+        }
+        assert_eq!(tx.try_send(cap), Err(TrySendError::Full(cap)));
+        assert_eq!(tx.capacity(), 0);
+
+        // Popping one item always makes room for one more, whatever the ring's blocks are
+        // doing underneath.
+        for i in cap..cap * 10 {
+            assert_eq!(rx.recv().await, Some(i - cap));
+            tx.try_send(i).unwrap();
+            assert!(tx.try_send(i).is_err());
+        }
+    }
+}
+
+#[tokio::test]
+async fn close() {
+    let (tx, mut rx) = channel(4);
+    let tx2 = tx.clone();
+    tx.send(1).await.unwrap();
+    tx2.send(2).await.unwrap();
+    drop(tx);
+    assert_eq!(rx.recv().await, Some(1));
+    drop(tx2);
+    assert_eq!(rx.recv().await, Some(2));
+    assert_eq!(rx.recv().await, None);
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+    let (tx, mut rx) = channel(4);
+    tx.send(1).await.unwrap();
+    let permit = tx.reserve().await.unwrap();
+    rx.close();
+    assert!(tx.is_closed());
+    assert_eq!(tx.send(2).await.unwrap_err().0, 2);
+    assert_eq!(tx.try_send(3), Err(TrySendError::Closed(3)));
+
+    // Queued messages and the outstanding permit still get through.
+    assert_eq!(rx.recv().await, Some(1));
+    permit.send(4);
+    assert_eq!(rx.recv().await, Some(4));
+    assert_eq!(rx.recv().await, None);
+}
+
+#[tokio::test]
+async fn reserve() {
+    let (tx, mut rx) = channel(2);
+    let p1 = tx.reserve().await.unwrap();
+    let p2 = tx.try_reserve().unwrap();
+    assert_eq!(tx.capacity(), 0);
+    assert!(matches!(tx.try_reserve(), Err(TrySendError::Full(()))));
+
+    drop(p1);
+    assert_eq!(tx.capacity(), 1);
+    p2.send(5);
+    assert_eq!(rx.recv().await, Some(5));
+    assert_eq!(tx.capacity(), 2);
+    assert_eq!(tx.max_capacity(), 2);
+}
+
+#[tokio::test]
+async fn recv_many() {
+    let (tx, mut rx) = channel(16);
+    for i in 0..10 {
+        tx.send(i).await.unwrap();
+    }
+
+    let mut buf = Vec::new();
+    assert_eq!(rx.recv_many(&mut buf, 0).await, 0);
+    assert_eq!(rx.recv_many(&mut buf, 4).await, 4);
+    assert_eq!(rx.recv_many(&mut buf, 100).await, 6);
+    assert_eq!(buf, (0..10).collect::<Vec<_>>());
+
+    drop(tx);
+    assert_eq!(rx.recv_many(&mut buf, 4).await, 0);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn blocked_sender_wakes() {
+    let (tx, mut rx) = channel(1);
+    tx.send(0).await.unwrap();
+
+    let sender = tokio::spawn(async move {
+        for i in 1..100 {
+            tx.send(i).await.unwrap();
+        }
+    });
+    for i in 0..100 {
+        assert_eq!(rx.recv().await, Some(i));
+    }
+    sender.await.unwrap();
+    assert_eq!(rx.recv().await, None);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn spsc() {
+    const COUNT: usize = 100_000;
+
+    let (tx, mut rx) = channel(8);
+    let producer = tokio::spawn(async move {
+        for i in 0..COUNT {
+            tx.send(i).await.unwrap();
+        }
+    });
+    for i in 0..COUNT {
+        assert_eq!(rx.recv().await, Some(i));
+    }
+    assert_eq!(rx.recv().await, None);
+    producer.await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn mpsc() {
+    const COUNT: usize = 25_000;
+    const PTHREADS: usize = 8;
+
+    let (tx, mut rx) = channel(8);
+    let v = Arc::new((0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>());
+
+    for _ in 0..PTHREADS {
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            for i in 0..COUNT {
+                tx.send(i).await.unwrap();
+            }
+        });
+    }
+    drop(tx);
+
+    let mut buf = Vec::new();
+    while rx.recv_many(&mut buf, 32).await > 0 {
+        for n in buf.drain(..) {
+            v[n].fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    for c in v.iter() {
+        assert_eq!(c.load(Ordering::SeqCst), PTHREADS);
+    }
+}